@@ -0,0 +1,188 @@
+use crate::config::{config, BarEdge, BarLayer, BarSettings, OutputSettings};
+use crate::gl::GliumGLArea;
+use crate::modules::{self, Zones};
+use crate::sensors::power::{on_power_source_changed, power, PowerSource};
+use crate::utils::global;
+use colored::Colorize;
+use gtk4::{gdk::Monitor, prelude::*};
+use gtk4_layer_shell::{Edge, Layer, LayerShell};
+use std::collections::HashMap;
+
+/*
+ ██████╗  █████╗ ██████╗ ███████╗
+ ██╔══██╗██╔══██╗██╔══██╗██╔════╝
+ ██████╔╝███████║██████╔╝███████╗
+ ██╔══██╗██╔══██║██╔══██╗╚════██║
+ ██████╔╝██║  ██║██║  ██║███████║
+ ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝
+
+    One bar per monitor, each with its own gl area and widget tree.
+    The sensors and the tray client are shared by all of them.
+*/
+
+#[derive(Clone)]
+pub struct Bar {
+    pub window: gtk4::ApplicationWindow,
+    pub area: GliumGLArea,
+    pub zones: Zones,
+}
+
+global!(bars, HashMap<Monitor, Bar>, HashMap::new());
+global!(bar_signals, bool, false);
+
+pub fn init_bars() {
+    if !*bar_signals() {
+        *bar_signals() = true;
+        let display = gtk4::gdk::Display::default().expect("Could not connect to a display.");
+        display.monitors().connect("items-changed", true, |_| {
+            diff_bars();
+            None
+        });
+        on_power_source_changed(|_| {
+            for bar in bars().values() {
+                apply_power_class(bar);
+            }
+        });
+    }
+    diff_bars();
+}
+
+/// Creates bars for new (allowed) monitors and destroys the ones that went away.
+pub fn diff_bars() {
+    let display = gtk4::gdk::Display::default().unwrap();
+    let outputs = &config().settings.bar.outputs;
+    let mut wanted: Vec<Monitor> = vec![];
+    for m in &display.monitors() {
+        let monitor = m.unwrap().downcast::<Monitor>().unwrap();
+        if is_allowed(outputs, &monitor) {
+            wanted.push(monitor);
+        }
+    }
+
+    let stale = bars()
+        .keys()
+        .filter(|m| !wanted.contains(m))
+        .cloned()
+        .collect::<Vec<_>>();
+    for monitor in &stale {
+        let bar = bars().remove(monitor).unwrap();
+        eprintln!(
+            "[{}]: {}({})",
+            "bar".green(),
+            "destroy".red(),
+            monitor.connector().unwrap_or_default()
+        );
+        bar.zones.clear();
+        bar.window.destroy();
+    }
+
+    let mut created = false;
+    for monitor in wanted {
+        if !bars().contains_key(&monitor) {
+            let bar = create_bar(&monitor);
+            bars().insert(monitor, bar);
+            created = true;
+        }
+    }
+
+    if created || !stale.is_empty() {
+        modules::apply_layout(&config().settings.modules);
+    }
+}
+
+fn is_allowed(outputs: &OutputSettings, monitor: &Monitor) -> bool {
+    let connector = monitor.connector().unwrap_or_default().to_string();
+    let model = monitor.model().unwrap_or_default().to_string();
+    let matches = |name: &String| *name == connector || *name == model;
+    if outputs.deny.iter().any(matches) {
+        return false;
+    }
+    outputs.allow.is_empty() || outputs.allow.iter().any(matches)
+}
+
+fn create_bar(monitor: &Monitor) -> Bar {
+    eprintln!(
+        "[{}]: {}({}) {} [{}]",
+        "bar".green(),
+        "create".yellow(),
+        monitor.connector().unwrap_or_default(),
+        monitor.model().unwrap_or_default(),
+        monitor.geometry().width()
+    );
+
+    let window = gtk4::ApplicationWindow::new(crate::application());
+
+    window.init_layer_shell();
+    window.set_title(Some(env!("CARGO_PKG_NAME")));
+    window.set_monitor(monitor);
+    // the namespace can only be set before the surface is mapped
+    window.set_namespace(&config().settings.bar.namespace);
+
+    let container = gtk4::Overlay::new();
+    window.set_child(Some(&container));
+
+    let area = GliumGLArea::default();
+    area.set_hexpand(true);
+    container.set_child(Some(&area));
+
+    let zones = Zones::new();
+    container.add_overlay(&zones.container);
+
+    let bar = Bar {
+        window,
+        area,
+        zones,
+    };
+    apply_geometry(&bar, &config().settings.bar);
+    apply_power_class(&bar);
+    bar.window.present();
+    bar
+}
+
+/// `on-ac` or `on-battery` on the window, for the stylesheet.
+fn apply_power_class(bar: &Bar) {
+    let source = power().source;
+    for candidate in [PowerSource::Ac, PowerSource::Battery] {
+        let class = format!("on-{}", candidate.name());
+        match source == Some(candidate) {
+            true => bar.window.add_css_class(&class),
+            false => bar.window.remove_css_class(&class),
+        }
+    }
+}
+
+pub fn apply_geometry_all() {
+    for bar in bars().values() {
+        apply_geometry(bar, &config().settings.bar);
+    }
+}
+
+/// The width is left to the anchors, so the bar always spans the whole monitor.
+fn apply_geometry(bar: &Bar, settings: &BarSettings) {
+    let window = &bar.window;
+
+    window.set_layer(match settings.layer {
+        BarLayer::Background => Layer::Background,
+        BarLayer::Bottom => Layer::Bottom,
+        BarLayer::Top => Layer::Top,
+        BarLayer::Overlay => Layer::Overlay,
+    });
+
+    match settings.exclusive_zone {
+        Some(zone) => window.set_exclusive_zone(zone),
+        None => window.auto_exclusive_zone_enable(),
+    }
+    window.set_height_request(settings.height);
+    bar.area.set_height_request(settings.height);
+
+    window.set_margin(Edge::Top, settings.margin.top);
+    window.set_margin(Edge::Right, settings.margin.right);
+    window.set_margin(Edge::Bottom, settings.margin.bottom);
+    window.set_margin(Edge::Left, settings.margin.left);
+
+    let top = settings.edge == BarEdge::Top;
+    window.set_anchor(Edge::Top, top);
+    window.set_anchor(Edge::Right, true);
+    window.set_anchor(Edge::Left, true);
+    window.set_anchor(Edge::Bottom, !top);
+}
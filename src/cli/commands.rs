@@ -0,0 +1,364 @@
+use super::Command;
+use crate::config::{self, format, leaves, read_config, read_layers, Settings};
+use crate::gl::uniform::{read_pixel, write_uniforms, SensorValues, HIDDEN_ROWS, LOAD_ROWS};
+use crate::sensors::{self, detect::Value, registry, Detected};
+use colored::Colorize;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    mem::offset_of,
+    path::{Path, PathBuf},
+    process::exit,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/*
+  ██████╗ ██████╗ ███╗   ███╗███╗   ███╗ █████╗ ███╗   ██╗██████╗ ███████╗
+ ██╔════╝██╔═══██╗████╗ ████║████╗ ████║██╔══██╗████╗  ██║██╔══██╗██╔════╝
+ ██║     ██║   ██║██╔████╔██║██╔████╔██║███████║██╔██╗ ██║██║  ██║███████╗
+ ██║     ██║   ██║██║╚██╔╝██║██║╚██╔╝██║██╔══██║██║╚██╗██║██║  ██║╚════██║
+ ╚██████╗╚██████╔╝██║ ╚═╝ ██║██║ ╚═╝ ██║██║  ██║██║ ╚████║██████╔╝███████║
+  ╚═════╝ ╚═════╝ ╚═╝     ╚═╝╚═╝     ╚═╝╚═╝  ╚═╝╚═╝  ╚═══╝╚═════╝ ╚══════╝
+
+    Everything in here runs before (and instead of) the gtk application,
+    so it works over ssh and on machines without a display.
+*/
+
+pub async fn run(command: Command) -> ! {
+    let ok = match command {
+        Command::Run => unreachable!(),
+        Command::CheckConfig => check_config(),
+        Command::ShowConfig => show_config(),
+        Command::ConvertConfig(file) => convert_config(file),
+        Command::PrintDefaults(which) => print_defaults(which),
+        Command::ListSensors { json, detected } => match detected {
+            true => list_detected(json).await,
+            false => list_sensors(json).await,
+        },
+        Command::DumpUniforms => dump_uniforms().await,
+    };
+    exit(if ok { 0 } else { 1 });
+}
+
+fn report(what: &str, errors: &[String]) -> bool {
+    if errors.is_empty() {
+        println!("[{}]: {}", what.green(), "ok".green());
+    }
+    for error in errors {
+        println!("[{}]: {}", what.green(), error.red());
+    }
+    errors.is_empty()
+}
+
+/*
+  ██████╗██╗  ██╗███████╗ ██████╗██╗  ██╗
+ ██╔════╝██║  ██║██╔════╝██╔════╝██║ ██╔╝
+ ██║     ███████║█████╗  ██║     █████╔╝
+ ██║     ██╔══██║██╔══╝  ██║     ██╔═██╗
+ ╚██████╗██║  ██║███████╗╚██████╗██║  ██╗
+  ╚═════╝╚═╝  ╚═╝╚══════╝ ╚═════╝╚═╝  ╚═╝
+*/
+
+fn check_config() -> bool {
+    let settings = match read_config(&config::file(), &config::dir()) {
+        Ok(settings) => settings,
+        Err(err) => return report("config", &[err.to_string()]),
+    };
+    let config_ok = report("config", &[]);
+    let stylesheet_ok = report(
+        "stylesheet",
+        &check_stylesheet(&config::stylesheet_file(&settings)),
+    );
+    let shaders_ok = report("shaders", &check_shaders(&settings));
+    config_ok && stylesheet_ok && shaders_ok
+}
+
+/// Every effective value, followed by the layer that set it.
+fn show_config() -> bool {
+    let layers = match read_layers(&config::file(), &config::dir()) {
+        Ok(layers) => layers,
+        Err(err) => return report("config", &[err.to_string()]),
+    };
+    println!("{}", "layers (later wins):".green());
+    for layer in &layers.0 {
+        println!("  {}", layer.file.display());
+    }
+    println!();
+    let merged = layers.merged();
+    let leaves = leaves(&merged);
+    let width = leaves.iter().map(|(path, _)| path.len()).max().unwrap_or(0);
+    for (path, value) in &leaves {
+        println!(
+            "{:width$}  {}  {}",
+            path.green(),
+            value,
+            layers.origin(path).display().to_string().dimmed(),
+            width = width
+        );
+    }
+    true
+}
+
+/// Only converts the file itself, not the merged layers,
+/// so the result can replace it one to one.
+fn convert_config(file: Option<PathBuf>) -> bool {
+    let file = file.unwrap_or_else(config::file);
+    let converted = std::fs::read_to_string(&file)
+        .map_err(|err| err.to_string())
+        .and_then(|text| format::Format::of(&file).parse(&text))
+        .and_then(|value| format::to_toml(&value));
+    match converted {
+        Ok(toml) => print!("{}", toml),
+        Err(err) => return report("config", &[format!("{}: {}", file.display(), err)]),
+    }
+    true
+}
+
+fn check_stylesheet(path: &Path) -> Vec<String> {
+    if let Err(err) = std::fs::read_to_string(path) {
+        return vec![format!("{}: {}", path.display(), err)];
+    }
+    if gtk4::init().is_err() {
+        println!(
+            "[{}]: {}",
+            "stylesheet".green(),
+            "no display, skipping the css parser".yellow()
+        );
+        return vec![];
+    }
+    let errors: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]));
+    let provider = gtk4::CssProvider::new();
+    let collect = errors.clone();
+    let file = path.display().to_string();
+    provider.connect_parsing_error(move |_, section, error| {
+        let location = section.start_location();
+        collect.borrow_mut().push(format!(
+            "{}:{}:{}: {}",
+            file,
+            location.lines() + 1,
+            location.line_chars() + 1,
+            error
+        ));
+    });
+    provider.load_from_path(path);
+    let errors = errors.borrow().clone();
+    errors
+}
+
+fn check_shaders(settings: &Settings) -> Vec<String> {
+    let mut errors = vec![];
+    for shader in &settings.shaders {
+        let source = match std::fs::read_to_string(&shader.path) {
+            Ok(source) => source,
+            Err(err) => {
+                errors.push(format!("{}: {}: {}", shader.name, shader.path, err));
+                continue;
+            }
+        };
+        if !source.trim_start().starts_with("#version") {
+            errors.push(format!(
+                "{}: {}: missing #version",
+                shader.name, shader.path
+            ));
+        }
+        if !source.contains("void main") {
+            errors.push(format!("{}: {}: missing main()", shader.name, shader.path));
+        }
+        if !source.contains("uniform sensors") {
+            errors.push(format!(
+                "{}: {}: missing the sensors uniform block",
+                shader.name, shader.path
+            ));
+        }
+    }
+    errors
+}
+
+/*
+ ██████╗ ███████╗███████╗ █████╗ ██╗   ██╗██╗  ████████╗███████╗
+ ██╔══██╗██╔════╝██╔════╝██╔══██╗██║   ██║██║  ╚══██╔══╝██╔════╝
+ ██║  ██║█████╗  █████╗  ███████║██║   ██║██║     ██║   ███████╗
+ ██║  ██║██╔══╝  ██╔══╝  ██╔══██║██║   ██║██║     ██║   ╚════██║
+ ██████╔╝███████╗██║     ██║  ██║╚██████╔╝███████╗██║   ███████║
+ ╚═════╝ ╚══════╝╚═╝     ╚═╝  ╚═╝ ╚═════╝ ╚══════╝╚═╝   ╚══════╝
+*/
+
+/// Plain json unless asked for css, so the output can be piped into a config.
+fn print_defaults(which: Option<String>) -> bool {
+    match which.as_deref() {
+        Some("css") => println!("{}", include_str!("../config/defaults.css")),
+        _ => println!("{}", include_str!("../config/defaults.json")),
+    }
+    true
+}
+
+/*
+ ███████╗███████╗███╗   ██╗███████╗ ██████╗ ██████╗ ███████╗
+ ██╔════╝██╔════╝████╗  ██║██╔════╝██╔═══██╗██╔══██╗██╔════╝
+ ███████╗█████╗  ██╔██╗ ██║███████╗██║   ██║██████╔╝███████╗
+ ╚════██║██╔══╝  ██║╚██╗██║╚════██║██║   ██║██╔══██╗╚════██║
+ ███████║███████╗██║ ╚████║███████║╚██████╔╝██║  ██║███████║
+ ╚══════╝╚══════╝╚═╝  ╚═══╝╚══════╝ ╚═════╝ ╚═╝  ╚═╝╚══════╝
+*/
+
+async fn list_sensors(json: bool) -> bool {
+    sample_twice(Duration::from_millis(250)).await;
+    let entries = registry().entries();
+    if json {
+        let map = entries
+            .iter()
+            .map(|entry| {
+                let value = match entry.available {
+                    true => serde_json::Value::from(entry.reading.values()),
+                    false => serde_json::Value::Null,
+                };
+                let sensor = serde_json::json!({
+                    "available": entry.available,
+                    "value": value,
+                    "text": entry.sensor.text(),
+                    "unit": format!("{:?}", entry.sensor.unit()).to_lowercase(),
+                    "source": entry.sensor.source(),
+                });
+                (entry.sensor.name().to_string(), sensor)
+            })
+            .collect::<serde_json::Map<_, _>>();
+        println!("{}", serde_json::to_string_pretty(&map).unwrap());
+        return true;
+    }
+    let width = entries
+        .iter()
+        .map(|entry| entry.sensor.name().len())
+        .max()
+        .unwrap_or(0);
+    for entry in entries {
+        let name = format!("{:width$}", entry.sensor.name(), width = width);
+        match entry.display() {
+            Some(value) => println!(
+                "{}  {:>10}  {}",
+                name.green(),
+                value,
+                entry.sensor.source().dimmed()
+            ),
+            None => println!("{}  {:>10}", name.red(), "unavailable"),
+        }
+    }
+    true
+}
+
+/// What the sensors are built from, e.g. `gpu[0].model` or `disk.count`.
+async fn list_detected(json: bool) -> bool {
+    let detected = Detected::detect().await;
+    let found = detected.found().iter().collect::<BTreeMap<_, _>>();
+    if json {
+        let map = found
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Value::U64(x) => serde_json::Value::from(*x),
+                    Value::String(x) => serde_json::Value::from(x.as_str()),
+                };
+                (key.to_string(), value)
+            })
+            .collect::<serde_json::Map<_, _>>();
+        println!("{}", serde_json::to_string_pretty(&map).unwrap());
+        return true;
+    }
+    let width = found.keys().map(|key| key.len()).max().unwrap_or(0);
+    for (key, value) in found {
+        let key = format!("{:width$}", key, width = width);
+        println!("{}  {}", key.green(), value);
+    }
+    true
+}
+
+/// cpu and network loads are deltas, so they need a second sample
+async fn sample_twice(delay: Duration) {
+    sensors::detect().await;
+    registry().sample(Instant::now());
+    tokio::time::sleep(delay).await;
+    registry().sample(Instant::now());
+}
+
+/*
+ ██╗   ██╗███╗   ██╗██╗███████╗ ██████╗ ██████╗ ███╗   ███╗███████╗
+ ██║   ██║████╗  ██║██║██╔════╝██╔═══██╗██╔══██╗████╗ ████║██╔════╝
+ ██║   ██║██╔██╗ ██║██║█████╗  ██║   ██║██████╔╝██╔████╔██║███████╗
+ ██║   ██║██║╚██╗██║██║██╔══╝  ██║   ██║██╔══██╗██║╚██╔╝██║╚════██║
+ ╚██████╔╝██║ ╚████║██║██║     ╚██████╔╝██║  ██║██║ ╚═╝ ██║███████║
+  ╚═════╝ ╚═╝  ╚═══╝╚═╝╚═╝      ╚═════╝ ╚═╝  ╚═╝╚═╝     ╚═╝╚══════╝
+*/
+
+async fn dump_uniforms() -> bool {
+    sample_twice(Duration::from_millis(500)).await;
+
+    let mut values = SensorValues::new();
+    write_uniforms(&mut values, 0);
+
+    macro_rules! field {
+        ($name:ident) => {
+            println!(
+                "{:>5} {:>5}  {:12} {:?}",
+                offset_of!(SensorValues, $name),
+                std::mem::size_of_val(&values.$name),
+                stringify!($name).green(),
+                values.$name
+            );
+        };
+    }
+
+    println!(
+        "SensorValues: {} bytes\n{:>5} {:>5}  {:12} {}",
+        std::mem::size_of::<SensorValues>(),
+        "offs",
+        "size",
+        "field",
+        "value"
+    );
+    field!(width);
+    field!(height);
+    field!(gauge_count);
+    field!(gauge_value);
+    field!(gauge_color);
+    field!(load_ptr);
+    field!(load_count);
+    field!(load_color);
+    field!(gauge_available);
+    field!(load_available);
+    field!(battery);
+    field!(battery_available);
+    field!(power_source);
+    field!(power_changed);
+    field!(disk);
+    field!(disk_available);
+    field!(memory);
+    field!(memory_available);
+    field!(cpu);
+    field!(cpu_available);
+    field!(gpu);
+    field!(gpu_available);
+    println!(
+        "{:>5} {:>5}  {:12} {} rows x {} samples",
+        offset_of!(SensorValues, load),
+        std::mem::size_of_val(&values.load),
+        "load".green(),
+        values.load.len() / 64,
+        64 * 4
+    );
+    // the same rows in the same order as write_uniforms, past load_count they are not drawn
+    let cores = registry().values("cpu.load").len();
+    let cores = (0..cores).map(|i| format!("cpu.load[{}]", i));
+    let rows = LOAD_ROWS
+        .iter()
+        .chain(HIDDEN_ROWS)
+        .map(|row| row.to_string());
+    for (row, label) in cores.chain(rows).take(32).enumerate() {
+        let value = read_pixel(&values, row, values.load_ptr as usize);
+        let shown = match row < values.load_count as usize {
+            true => label.normal(),
+            false => label.dimmed(),
+        };
+        println!("{:>12}  load[{:>2}] {:>3}  {}", "", row, value, shown);
+    }
+    true
+}
@@ -0,0 +1,117 @@
+pub mod commands;
+
+use crate::utils::global_init;
+use std::path::PathBuf;
+use std::process::exit;
+
+/*
+  ██████╗██╗     ██╗
+ ██╔════╝██║     ██║
+ ██║     ██║     ██║
+ ██║     ██║     ██║
+ ╚██████╗███████╗██║
+  ╚═════╝╚══════╝╚═╝
+*/
+
+const USAGE: &str = "Usage: shaderbar [OPTIONS] [COMMAND]
+
+Commands:
+  check-config             validate config, stylesheet and shaders, exit non-zero on errors
+  show-config              print the merged config and the file each value came from
+  convert-config [file]    print a config (default: the current one) as toml
+  print-defaults [json|css]
+                           print the embedded defaults.json (default) or defaults.css
+  list-sensors [--json] [--detected]
+                           print the detected sensors, $config.sensors applied,
+                           or with --detected what detection found (paths, counts)
+  dump-uniforms            print the SensorValues layout and current values
+
+Options:
+  -c, --config <file>      use <file> instead of $XDG_CONFIG_HOME/shaderbar/config.json
+  -s, --stylesheet <file>  use <file> instead of the stylesheet from the config
+  -r, --root <dir>         read /sys and /proc below <dir> (default: $SHADERBAR_ROOT or /)
+  -h, --help               print this help";
+
+global_init!(args, Args, parse_args);
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub stylesheet: Option<PathBuf>,
+    pub root: Option<PathBuf>,
+    pub command: Command,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum Command {
+    #[default]
+    Run,
+    CheckConfig,
+    ShowConfig,
+    ConvertConfig(Option<PathBuf>),
+    PrintDefaults(Option<String>),
+    ListSensors {
+        json: bool,
+        detected: bool,
+    },
+    DumpUniforms,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        match (flag.as_str(), &mut args.command) {
+            ("-c" | "--config", _) => {
+                args.config = Some(value(&flag, inline.or_else(|| argv.next())))
+            }
+            ("-s" | "--stylesheet", _) => {
+                args.stylesheet = Some(value(&flag, inline.or_else(|| argv.next())))
+            }
+            ("-r" | "--root", _) => args.root = Some(value(&flag, inline.or_else(|| argv.next()))),
+            ("-h" | "--help", _) => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            ("check-config", Command::Run) => args.command = Command::CheckConfig,
+            ("show-config", Command::Run) => args.command = Command::ShowConfig,
+            ("convert-config", Command::Run) => args.command = Command::ConvertConfig(None),
+            ("print-defaults", Command::Run) => args.command = Command::PrintDefaults(None),
+            ("list-sensors", Command::Run) => {
+                args.command = Command::ListSensors {
+                    json: false,
+                    detected: false,
+                }
+            }
+            ("dump-uniforms", Command::Run) => args.command = Command::DumpUniforms,
+            ("json" | "css", Command::PrintDefaults(which @ None)) => *which = Some(flag.clone()),
+            ("--json", Command::ListSensors { json, .. }) => *json = true,
+            ("--detected", Command::ListSensors { detected, .. }) => *detected = true,
+            (_, Command::ConvertConfig(file @ None)) if !flag.starts_with('-') => {
+                *file = Some(value(&flag, Some(arg.clone())))
+            }
+            _ => usage_error(&format!("unknown argument: {}", arg)),
+        }
+    }
+    args
+}
+
+fn value(flag: &str, value: Option<String>) -> PathBuf {
+    let value = value.unwrap_or_else(|| usage_error(&format!("{} expects a path", flag)));
+    let path = PathBuf::from(value);
+    if path.is_relative() {
+        return std::env::current_dir().unwrap().join(path);
+    }
+    path
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    exit(2);
+}
@@ -0,0 +1,92 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/*
+ ███████╗ ██████╗ ██████╗ ███╗   ███╗ █████╗ ████████╗
+ ██╔════╝██╔═══██╗██╔══██╗████╗ ████║██╔══██╗╚══██╔══╝
+ █████╗  ██║   ██║██████╔╝██╔████╔██║███████║   ██║
+ ██╔══╝  ██║   ██║██╔══██╗██║╚██╔╝██║██╔══██║   ██║
+ ██║     ╚██████╔╝██║  ██║██║ ╚═╝ ██║██║  ██║   ██║
+ ╚═╝      ╚═════╝ ╚═╝  ╚═╝╚═╝     ╚═╝╚═╝  ╚═╝   ╚═╝
+
+    Every format is parsed into a serde_json::Value first, so merging,
+    origins and error paths work the same no matter what the file is.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+/// In order of preference, when a directory has more than one config.
+pub const EXTENSIONS: &[&str] = &["toml", "yaml", "yml", "json"];
+
+impl Format {
+    /// Anything that is not .toml, .yaml or .yml is read as JSON.
+    pub fn of(file: &Path) -> Format {
+        match file.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Format::Toml,
+            Some("yaml" | "yml") => Format::Yaml,
+            _ => Format::Json,
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> Result<Value, String> {
+        match self {
+            Format::Json => serde_json::from_str(text).map_err(|err| err.to_string()),
+            Format::Toml => toml::from_str(text).map_err(|err| err.to_string()),
+            Format::Yaml => serde_yaml::from_str(text).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+/// Every existing `<dir>/<stem>.<ext>`, the preferred one first.
+pub fn find(dir: &Path, stem: &str) -> Vec<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", stem, ext)))
+        .filter(|file| file.is_file())
+        .collect()
+}
+
+pub fn is_config(file: &Path) -> bool {
+    let ext = file.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    EXTENSIONS.contains(&ext)
+}
+
+/// TOML has no null, and a null option is the same as leaving it out.
+/// The `"*COMMENT*"` keys JSON needed become real comments.
+pub fn to_toml(value: &Value) -> Result<String, String> {
+    let toml = toml::to_string_pretty(&strip_nulls(value)).map_err(|err| err.to_string())?;
+    let lines = toml.lines().map(|line| {
+        let Some(comment) = line.trim_start().strip_prefix("\"*COMMENT*\" = ") else {
+            return line.to_string();
+        };
+        match toml::from_str::<toml::Table>(&format!("c = {}", comment)) {
+            Ok(table) => format!("# {}", table["c"].as_str().unwrap_or(comment)),
+            Err(_) => line.to_string(),
+        }
+    });
+    Ok(lines.collect::<Vec<_>>().join("\n") + "\n")
+}
+
+fn strip_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.clone(), strip_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(array) => Value::Array(
+            array
+                .iter()
+                .filter(|value| !value.is_null())
+                .map(strip_nulls)
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
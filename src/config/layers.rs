@@ -0,0 +1,241 @@
+use super::format::{self, Format};
+use super::ConfigError;
+use serde_json::{from_str, Map, Value};
+use std::path::{Path, PathBuf};
+
+/*
+ ██╗      █████╗ ██╗   ██╗███████╗██████╗ ███████╗
+ ██║     ██╔══██╗╚██╗ ██╔╝██╔════╝██╔══██╗██╔════╝
+ ██║     ███████║ ╚████╔╝ █████╗  ██████╔╝███████╗
+ ██║     ██╔══██║  ╚██╔╝  ██╔══╝  ██╔══██╗╚════██║
+ ███████╗██║  ██║   ██║   ███████╗██║  ██║███████║
+ ╚══════╝╚═╝  ╚═╝   ╚═╝   ╚══════╝╚═╝  ╚═╝╚══════╝
+
+    Later layers win:
+        embedded defaults.json
+        $XDG_CONFIG_DIRS/shaderbar/config.<ext>  (usually /etc/xdg)
+        the user config (or --config)
+        %config%/config.d/<name>.<ext>           (in file name order)
+        %config%/hosts/<hostname>.<ext>
+
+    <ext> is json, toml, yaml or yml, see format.rs.
+
+    Objects are merged key by key, everything else (arrays included)
+    is replaced as a whole. Only the user config has to exist.
+*/
+
+pub const DEFAULTS: &str = "<defaults>";
+pub const FRAGMENTS_DIR: &str = "config.d";
+pub const HOSTS_DIR: &str = "hosts";
+
+pub struct Layer {
+    pub file: PathBuf,
+    pub value: Value,
+}
+
+pub struct Layers(pub Vec<Layer>);
+
+pub fn read_layers(config_file: &Path, config_dir: &Path) -> Result<Layers, ConfigError> {
+    let defaults = from_str(include_str!("defaults.json")).unwrap();
+    let mut layers = vec![Layer {
+        file: PathBuf::from(DEFAULTS),
+        value: defaults,
+    }];
+    for file in system_files() {
+        push_optional(&mut layers, file)?;
+    }
+    layers.push(read_layer(config_file)?);
+    for file in fragment_files(config_dir) {
+        push_optional(&mut layers, file)?;
+    }
+    if let Some(file) = host_file(config_dir) {
+        push_optional(&mut layers, file)?;
+    }
+    Ok(Layers(layers))
+}
+
+/// Directories that can add or remove layers, for the file watcher.
+pub fn layer_dirs(config_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = system_dirs();
+    dirs.push(config_dir.join(FRAGMENTS_DIR));
+    dirs.push(config_dir.join(HOSTS_DIR));
+    dirs.into_iter().filter(|dir| dir.is_dir()).collect()
+}
+
+fn system_dirs() -> Vec<PathBuf> {
+    let dirs = std::env::var("XDG_CONFIG_DIRS").unwrap_or_default();
+    let dirs = match dirs.trim() {
+        "" => "/etc/xdg".to_string(),
+        dirs => dirs.to_string(),
+    };
+    // the first entry is the most important one, so it goes last
+    dirs.split(':')
+        .filter(|dir| !dir.is_empty())
+        .rev()
+        .map(|dir| Path::new(dir).join(super::CONFIG_DIR))
+        .collect()
+}
+
+fn system_files() -> Vec<PathBuf> {
+    system_dirs()
+        .into_iter()
+        .filter_map(|dir| format::find(&dir, super::CONFIG_NAME).into_iter().next())
+        .collect()
+}
+
+fn fragment_files(config_dir: &Path) -> Vec<PathBuf> {
+    let pattern = config_dir.join(FRAGMENTS_DIR).join("*");
+    let mut files = glob::glob(&pattern.to_string_lossy())
+        .map(|paths| paths.filter_map(Result::ok).collect::<Vec<_>>())
+        .unwrap_or_default();
+    files.retain(|file| format::is_config(file));
+    files.sort();
+    files
+}
+
+fn host_file(config_dir: &Path) -> Option<PathBuf> {
+    let hostname = whoami::fallible::hostname().ok()?;
+    format::find(&config_dir.join(HOSTS_DIR), &hostname)
+        .into_iter()
+        .next()
+}
+
+fn push_optional(layers: &mut Vec<Layer>, file: PathBuf) -> Result<(), ConfigError> {
+    if file.is_file() {
+        layers.push(read_layer(&file)?);
+    }
+    Ok(())
+}
+
+fn read_layer(file: &Path) -> Result<Layer, ConfigError> {
+    let text = std::fs::read_to_string(file).map_err(|error| ConfigError::Io {
+        file: file.to_path_buf(),
+        error,
+    })?;
+    let value = Format::of(file)
+        .parse(&text)
+        .map_err(|error| ConfigError::Syntax {
+            file: file.to_path_buf(),
+            error,
+        })?;
+    Ok(Layer {
+        file: file.to_path_buf(),
+        value,
+    })
+}
+
+/*
+ ███╗   ███╗███████╗██████╗  ██████╗ ███████╗
+ ████╗ ████║██╔════╝██╔══██╗██╔════╝ ██╔════╝
+ ██╔████╔██║█████╗  ██████╔╝██║  ███╗█████╗
+ ██║╚██╔╝██║██╔══╝  ██╔══██╗██║   ██║██╔══╝
+ ██║ ╚═╝ ██║███████╗██║  ██║╚██████╔╝███████╗
+ ╚═╝     ╚═╝╚══════╝╚═╝  ╚═╝ ╚═════╝ ╚══════╝
+*/
+
+impl Layers {
+    pub fn merged(&self) -> Value {
+        let mut merged = Value::Object(Map::new());
+        for layer in &self.0 {
+            merge(&mut merged, &layer.value);
+        }
+        merged
+    }
+
+    /// The file the value at `path` (like `$config.bar.height`) came from.
+    /// Paths that are set nowhere (a missing key) fall back to their parent.
+    pub fn origin(&self, path: &str) -> &Path {
+        let segments = segments(path);
+        for len in (1..=segments.len()).rev() {
+            let layer = self
+                .0
+                .iter()
+                .rev()
+                .find(|layer| lookup(&layer.value, &segments[..len]).is_some());
+            if let Some(layer) = layer {
+                return &layer.file;
+            }
+        }
+        // something is wrong with the document as a whole, blame the user config
+        self.0
+            .iter()
+            .rev()
+            .find(|layer| layer.file.as_os_str() != DEFAULTS)
+            .map(|layer| layer.file.as_path())
+            .unwrap_or(Path::new(DEFAULTS))
+    }
+}
+
+fn merge(into: &mut Value, from: &Value) {
+    match (into, from) {
+        (Value::Object(into), Value::Object(from)) => {
+            for (key, value) in from {
+                match into.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        into.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (into, from) => *into = from.clone(),
+    }
+}
+
+/// Every non-object value of `root` with its path, keys sorted.
+pub fn leaves(root: &Value) -> Vec<(String, &Value)> {
+    fn walk<'a>(path: String, value: &'a Value, out: &mut Vec<(String, &'a Value)>) {
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                for (key, value) in map {
+                    walk(format!("{}{}", path, key_segment(key)), value, out);
+                }
+            }
+            _ => out.push((path, value)),
+        }
+    }
+    let mut out = vec![];
+    walk("$config".to_string(), root, &mut out);
+    out
+}
+
+/// `.key`, or `["key"]` for keys like `cpu.temp` that would not survive `segments`.
+pub fn key_segment(key: &str) -> String {
+    match key.contains(['.', '[', ']']) {
+        true => format!("[{:?}]", key),
+        false => format!(".{}", key),
+    }
+}
+
+/// `$config.shaders[0].path` -> `["shaders", "0", "path"]`,
+/// `$config.sensors["cpu.temp"].max` -> `["sensors", "cpu.temp", "max"]`
+fn segments(path: &str) -> Vec<String> {
+    let mut rest = path.strip_prefix("$config").unwrap_or(path);
+    let mut segments = vec![];
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix("[\"") {
+            let end = quoted.find("\"]").unwrap_or(quoted.len());
+            segments.push(quoted[..end].to_string());
+            rest = quoted.get(end + 2..).unwrap_or("");
+            continue;
+        }
+        let trimmed = rest.trim_start_matches(['.', '[', ']']);
+        let end = trimmed.find(['.', '[', ']']).unwrap_or(trimmed.len());
+        if end > 0 {
+            segments.push(trimmed[..end].to_string());
+        }
+        rest = &trimmed[end..];
+    }
+    segments
+}
+
+fn lookup<'a>(root: &'a Value, segments: &[String]) -> Option<&'a Value> {
+    let mut value = root;
+    for segment in segments {
+        value = match value {
+            Value::Array(array) => array.get(segment.parse::<usize>().ok()?)?,
+            _ => value.get(segment)?,
+        };
+    }
+    Some(value)
+}
@@ -1,81 +1,141 @@
+pub mod format;
+mod layers;
+mod paths;
+mod schema;
+pub mod watch;
+
+use crate::cli::args;
 use crate::utils::*;
-use dirs::home_dir;
-use serde_json::{from_str, Value};
-use std::path::PathBuf;
-use tokio::fs::{create_dir_all, write};
+use colored::Colorize;
+use std::fs::{create_dir_all, write};
+use std::path::{Path, PathBuf};
+
+pub use layers::{layer_dirs, leaves, read_layers};
+pub use paths::resolve_path;
+pub use schema::*;
 
-pub const CONFIG_DIR: &str = ".config/shaderbar";
+pub const CONFIG_DIR: &str = "shaderbar";
+pub const CONFIG_NAME: &str = "config";
 pub const CONFIG_FILE: &str = "config.json";
 pub const CONFIG_STYLESHEET: &str = "theme.css";
 
-global_init_async!(config, Config, init_config);
+global_init!(config, Config, init_config);
 pub struct Config {
-    pub config: serde_json::Value,
+    pub settings: Settings,
     pub config_dir: PathBuf,
     pub config_file: PathBuf,
     pub stylesheet_file: PathBuf,
 }
 
-pub async fn init_config() -> Config {
-    let home = dirs::home_dir().unwrap();
-    let config_dir = home.join(CONFIG_DIR);
-    let config_file: PathBuf = config_dir.join(CONFIG_FILE);
-    let stylesheet_file: PathBuf = config_dir.join(CONFIG_STYLESHEET);
+pub fn init_config() -> Config {
+    let config_dir = dir();
+    let config_file: PathBuf = file();
+
+    if args().config.is_none() {
+        create_config_dir();
+        write_default_config();
+        write_default_styles();
+    }
 
-    create_config_dir().await;
-    write_default_config().await;
-    write_default_styles().await;
+    let settings = read_config(&config_file, &config_dir).unwrap_or_else(|err| {
+        eprintln!("[{}]: {}", "config".green(), err.to_string().red());
+        std::process::exit(1);
+    });
+    let stylesheet_file: PathBuf = stylesheet_file(&settings);
 
-    return Config {
-        config: read_config().await,
+    Config {
+        settings,
         config_dir,
         config_file,
         stylesheet_file,
-    };
+    }
 }
 
-fn dir() -> PathBuf {
-    home_dir().unwrap().join(CONFIG_DIR)
+/// `--config` wins, otherwise `$XDG_CONFIG_HOME/shaderbar` (or `~/.config/shaderbar`).
+pub fn dir() -> PathBuf {
+    if let Some(config_file) = &args().config {
+        return config_file.parent().unwrap_or(Path::new("/")).to_path_buf();
+    }
+    dirs::config_dir()
+        .unwrap_or_else(|| dirs::home_dir().unwrap().join(".config"))
+        .join(CONFIG_DIR)
 }
 
-fn file() -> PathBuf {
-    home_dir().unwrap().join(CONFIG_DIR).join(CONFIG_FILE)
+/// `--config` wins, otherwise the first of config.{toml,yaml,yml,json}
+/// in `dir()`, a new config is always written as config.json.
+pub fn file() -> PathBuf {
+    match &args().config {
+        Some(config_file) => config_file.clone(),
+        None => format::find(&dir(), CONFIG_NAME)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| dir().join(CONFIG_FILE)),
+    }
 }
 
 fn styles() -> PathBuf {
-    home_dir().unwrap().join(CONFIG_DIR).join(CONFIG_STYLESHEET)
+    dir().join(CONFIG_STYLESHEET)
 }
 
-async fn create_config_dir() {
-    early_return!(!dir().exists());
-    create_dir_all(dir()).await.unwrap();
+pub fn stylesheet_file(settings: &Settings) -> PathBuf {
+    match &args().stylesheet {
+        Some(stylesheet) => stylesheet.clone(),
+        None => PathBuf::from(&settings.stylesheet),
+    }
+}
+
+fn create_config_dir() {
+    early_return!(dir().exists());
+    create_dir_all(dir()).unwrap();
 }
 
-async fn write_default_config() {
+fn write_default_config() {
     let config_file = file();
     early_return!(!dir().exists());
+    warn_ambiguous_config();
     early_return!(config_file.exists());
     let default_config = include_str!("defaults.json");
-    write(config_file.clone(), default_config).await.unwrap();
+    write(config_file.clone(), default_config).unwrap();
     println!("Config file created at: {:?}", config_file.clone());
 }
 
-async fn write_default_styles() {
+fn warn_ambiguous_config() {
+    let found = format::find(&dir(), CONFIG_NAME);
+    early_return!(found.len() < 2);
+    eprintln!(
+        "[{}]: {}",
+        "config".green(),
+        format!(
+            "found {} configs, using {:?}",
+            found.len(),
+            found[0].file_name().unwrap_or_default()
+        )
+        .yellow()
+    );
+}
+
+fn write_default_styles() {
     let stylesheet_file = styles();
     early_return!(!dir().exists());
     early_return!(stylesheet_file.exists());
     let default_stylesheet = include_str!("defaults.css");
-    write(stylesheet_file.clone(), default_stylesheet)
-        .await
-        .unwrap();
+    write(stylesheet_file.clone(), default_stylesheet).unwrap();
     println!("Stylesheet file created at: {:?}", stylesheet_file.clone());
 }
 
-async fn read_config() -> Value {
-    let config_file = file();
-    let config = tokio::fs::read_to_string(config_file.clone())
-        .await
-        .unwrap();
-    let config: Value = from_str(&config).unwrap();
-    return config;
+/// Reads and merges every layer, see `layers.rs` for the order.
+pub fn read_config(config_file: &Path, config_dir: &Path) -> Result<Settings, ConfigError> {
+    let layers = read_layers(config_file, config_dir)?;
+    let mut settings = Settings::from_value(layers.merged(), &layers)?;
+    settings.resolve_paths(config_dir)?;
+    Ok(settings)
+}
+
+pub fn reload_config() -> Result<(), ConfigError> {
+    let config = config();
+    let settings = read_config(&config.config_file, &config.config_dir)?;
+    config.stylesheet_file = stylesheet_file(&settings);
+    config.settings = settings;
+    println!("Config reloaded from: {:?}", config.config_file.clone());
+    Ok(())
 }
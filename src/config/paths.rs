@@ -0,0 +1,84 @@
+use super::ConfigError;
+use std::path::{Path, PathBuf};
+
+/*
+ ██████╗  █████╗ ████████╗██╗  ██╗███████╗
+ ██╔══██╗██╔══██╗╚══██╔══╝██║  ██║██╔════╝
+ ██████╔╝███████║   ██║   ███████║███████╗
+ ██╔═══╝ ██╔══██║   ██║   ██╔══██║╚════██║
+ ██║     ██║  ██║   ██║   ██║  ██║███████║
+ ╚═╝     ╚═╝  ╚═╝   ╚═╝   ╚═╝  ╚═╝╚══════╝
+
+    %user_config%  the users config home (think $HOME/.config)
+    %config%       the shaderbar config dir (think %user_config%/shaderbar)
+    ~              the users home directory
+    $VAR ${VAR}    environment variables
+    relative paths are relative to %config%
+*/
+
+pub fn resolve_path(key: &str, value: &str, config_dir: &Path) -> Result<PathBuf, ConfigError> {
+    let unresolved = |placeholder: &str, reason: &str| ConfigError::Unresolved {
+        path: key.to_string(),
+        value: value.to_string(),
+        placeholder: placeholder.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut out = String::new();
+    let mut rest = value;
+
+    if rest == "~" || rest.starts_with("~/") {
+        let home = dirs::home_dir().ok_or_else(|| unresolved("~", "no home directory"))?;
+        out.push_str(&home.to_string_lossy());
+        rest = &rest[1..];
+    }
+
+    while let Some(start) = rest.find(['%', '$']) {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let (replacement, len) = match tail.as_bytes()[0] {
+            b'%' => {
+                let end = tail[1..]
+                    .find('%')
+                    .ok_or_else(|| unresolved(tail, "missing closing %"))?;
+                let placeholder = &tail[..end + 2];
+                let replacement = match placeholder {
+                    "%user_config%" => dirs::config_dir()
+                        .ok_or_else(|| unresolved(placeholder, "no config home"))?,
+                    "%config%" => config_dir.to_path_buf(),
+                    _ => return Err(unresolved(placeholder, "unknown placeholder")),
+                };
+                (replacement.to_string_lossy().to_string(), end + 2)
+            }
+            _ => {
+                let (name, len) = if tail.starts_with("${") {
+                    let end = tail
+                        .find('}')
+                        .ok_or_else(|| unresolved(tail, "missing closing }"))?;
+                    (&tail[2..end], end + 1)
+                } else {
+                    let end = tail[1..]
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .map(|end| end + 1)
+                        .unwrap_or(tail.len());
+                    (&tail[1..end], end)
+                };
+                let placeholder = &tail[..len];
+                if name.is_empty() {
+                    return Err(unresolved(placeholder, "empty variable name"));
+                }
+                let replacement = std::env::var(name)
+                    .map_err(|_| unresolved(placeholder, "environment variable is not set"))?;
+                (replacement, len)
+            }
+        };
+        out.push_str(&replacement);
+        rest = &tail[len..];
+    }
+    out.push_str(rest);
+
+    let path = PathBuf::from(out);
+    if path.is_relative() {
+        return Ok(config_dir.join(path));
+    }
+    Ok(path)
+}
@@ -0,0 +1,629 @@
+use super::layers::{key_segment, Layers};
+use super::paths::resolve_path;
+use serde::Deserialize;
+use serde_json::Value;
+use serde_path_to_error::Segment;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/*
+ ███████╗ ██████╗██╗  ██╗███████╗███╗   ███╗ █████╗
+ ██╔════╝██╔════╝██║  ██║██╔════╝████╗ ████║██╔══██╗
+ ███████╗██║     ███████║█████╗  ██╔████╔██║███████║
+ ╚════██║██║     ██╔══██║██╔══╝  ██║╚██╔╝██║██╔══██║
+ ███████║╚██████╗██║  ██║███████╗██║ ╚═╝ ██║██║  ██║
+ ╚══════╝ ╚═════╝╚═╝  ╚═╝╚══════╝╚═╝     ╚═╝╚═╝  ╚═╝
+
+    Every section has a default, so a config.json only needs the keys it
+    actually wants to change. Unknown keys (like "*COMMENT*") are ignored.
+*/
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub bar: BarSettings,
+    pub modules: ModulesSettings,
+    pub wallpaper: WallpaperSettings,
+    pub shaders: Vec<ShaderSettings>,
+    pub stylesheet: String,
+    /// Keyed like the output of `shaderbar list-sensors`, e.g. `cpu.temp`.
+    pub sensors: BTreeMap<String, SensorSettings>,
+    pub power: PowerSettings,
+    pub network: NetworkSettings,
+    pub disk: DiskSettings,
+    pub processes: ProcessesSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct BarSettings {
+    pub edge: BarEdge,
+    pub height: i32,
+    pub margin: Margins,
+    pub layer: BarLayer,
+    /// `null` lets the compositor reserve exactly the bar height.
+    pub exclusive_zone: Option<i32>,
+    pub namespace: String,
+    pub outputs: OutputSettings,
+}
+
+/// Outputs are matched by connector (`DP-1`) or model name.
+/// `deny` wins over `allow`, an empty `allow` list means every output.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BarEdge {
+    Top,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BarLayer {
+    Background,
+    Bottom,
+    Top,
+    Overlay,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Margins {
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub left: i32,
+}
+
+/// Names accepted in `$config.modules.{left,center,right}`.
+pub const MODULES: &[&str] = &[
+    "tray",
+    "user-host",
+    "date-time",
+    "window-name",
+    "network",
+    "memory",
+    "processes",
+];
+
+/// A name from `MODULES` or `sensor:<name>` for any registry sensor.
+fn is_module(name: &str) -> bool {
+    match name.strip_prefix("sensor:") {
+        Some(sensor) => !sensor.is_empty(),
+        None => MODULES.contains(&name),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ModulesSettings {
+    pub left: Vec<String>,
+    pub center: Vec<String>,
+    pub right: Vec<String>,
+    pub spacing: i32,
+    pub margin_left: i32,
+    pub margin_right: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct WallpaperSettings {
+    pub enabled: bool,
+    pub path: String,
+}
+
+/// What happens when the laptop is plugged in or unplugged.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PowerSettings {
+    /// Flash the bar on every change.
+    pub flash: bool,
+    /// Frames per second while on battery, 30 on AC.
+    pub battery_fps: u32,
+    /// Runs through `sh -c`, $SHADERBAR_POWER_SOURCE is `ac` or `battery`.
+    pub hook: Option<String>,
+}
+
+/// Interfaces are picked by name (`wlan0`) or kind: loopback, ethernet,
+/// wifi, wwan, tunnel, bridge or virtual. `ignore` wins over `graph`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub graph: Vec<String>,
+    pub ignore: Vec<String>,
+    /// What fills the rx and tx graphs, a `max` in $config.sensors wins.
+    pub scale: NetworkScale,
+    /// The half-life of a `decay` peak, the length of a `window`.
+    pub peak_seconds: f64,
+    /// The smallest full scale in bytes/s, an idle link would graph noise.
+    pub min_peak: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkScale {
+    /// The peak rate, fading away.
+    Decay,
+    /// The peak rate of the last `peak_seconds`.
+    Window,
+    /// The link speed, `decay` for links without one (wifi, tunnels).
+    Link,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DiskSettings {
+    /// Filesystems for `disk.usage`, any directory on them works.
+    pub mounts: Vec<String>,
+    /// Block devices like `nvme0n1`, empty means every disk (no loop, zram or dm devices).
+    pub devices: Vec<String>,
+    /// The half-life of the read and write peaks.
+    pub peak_seconds: f64,
+    /// The smallest full scale in bytes/s.
+    pub min_peak: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProcessesSettings {
+    /// How many processes the top lists by cpu and by memory hold.
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShaderSettings {
+    pub name: String,
+    pub path: String,
+}
+
+/// Every field is optional, whatever is left out comes from the
+/// defaults for that kind of sensor (see `sensors/scale.rs`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SensorSettings {
+    pub enabled: bool,
+    /// Replaces the detected path.
+    pub path: Option<String>,
+    /// The unit `min` and `max` are given in.
+    pub unit: Option<SensorUnit>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub curve: SensorCurve,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorUnit {
+    Raw,
+    Celsius,
+    Fahrenheit,
+    Rpm,
+    Percent,
+    Watts,
+    Seconds,
+    /// bytes per second
+    Bytes,
+    Mhz,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorCurve {
+    #[default]
+    Linear,
+    Sqrt,
+    Square,
+    Log,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            bar: BarSettings::default(),
+            modules: ModulesSettings::default(),
+            wallpaper: WallpaperSettings::default(),
+            shaders: vec![],
+            stylesheet: "theme.css".to_string(),
+            sensors: BTreeMap::new(),
+            power: PowerSettings::default(),
+            network: NetworkSettings::default(),
+            disk: DiskSettings::default(),
+            processes: ProcessesSettings::default(),
+        }
+    }
+}
+
+impl Default for BarSettings {
+    fn default() -> Self {
+        BarSettings {
+            edge: BarEdge::Top,
+            height: 24,
+            margin: Margins::default(),
+            layer: BarLayer::Top,
+            exclusive_zone: None,
+            namespace: env!("CARGO_PKG_NAME").to_string(),
+            outputs: OutputSettings::default(),
+        }
+    }
+}
+
+impl Default for ModulesSettings {
+    fn default() -> Self {
+        ModulesSettings {
+            left: vec![
+                "tray".to_string(),
+                "user-host".to_string(),
+                "date-time".to_string(),
+                "window-name".to_string(),
+            ],
+            center: vec![],
+            right: vec![],
+            spacing: 0,
+            margin_left: 16,
+            margin_right: 0,
+        }
+    }
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        PowerSettings {
+            flash: true,
+            battery_fps: 30,
+            hook: None,
+        }
+    }
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            graph: vec![
+                "ethernet".to_string(),
+                "wifi".to_string(),
+                "wwan".to_string(),
+            ],
+            ignore: vec![],
+            scale: NetworkScale::Decay,
+            peak_seconds: 10.0,
+            min_peak: 16384.0,
+        }
+    }
+}
+
+impl Default for DiskSettings {
+    fn default() -> Self {
+        DiskSettings {
+            mounts: vec!["/".to_string()],
+            devices: vec![],
+            peak_seconds: 10.0,
+            min_peak: 1048576.0,
+        }
+    }
+}
+
+impl Default for ProcessesSettings {
+    fn default() -> Self {
+        ProcessesSettings { count: 5 }
+    }
+}
+
+impl Default for SensorSettings {
+    fn default() -> Self {
+        SensorSettings {
+            enabled: true,
+            path: None,
+            unit: None,
+            min: None,
+            max: None,
+            curve: SensorCurve::Linear,
+        }
+    }
+}
+
+impl Default for WallpaperSettings {
+    fn default() -> Self {
+        WallpaperSettings {
+            enabled: false,
+            path: "%user_config%/wallpaper.png".to_string(),
+        }
+    }
+}
+
+/*
+ ██╗   ██╗ █████╗ ██╗     ██╗██████╗  █████╗ ████████╗███████╗
+ ██║   ██║██╔══██╗██║     ██║██╔══██╗██╔══██╗╚══██╔══╝██╔════╝
+ ██║   ██║███████║██║     ██║██║  ██║███████║   ██║   █████╗
+ ╚██╗ ██╔╝██╔══██║██║     ██║██║  ██║██╔══██║   ██║   ██╔══╝
+  ╚████╔╝ ██║  ██║███████╗██║██████╔╝██║  ██║   ██║   ███████╗
+   ╚═══╝  ╚═╝  ╚═╝╚══════╝╚═╝╚═════╝ ╚═╝  ╚═╝   ╚═╝   ╚══════╝
+*/
+
+impl Settings {
+    /// Errors point at the layer the offending value came from.
+    pub fn from_value(root: Value, layers: &Layers) -> Result<Settings, ConfigError> {
+        let settings: Settings =
+            serde_path_to_error::deserialize(root.clone()).map_err(|error| {
+                let message = error.inner().to_string();
+                let expected = match message.split_once(", expected ") {
+                    Some((_, expected)) => expected.to_string(),
+                    None => message.clone(),
+                };
+                let found = match lookup(&root, error.path()) {
+                    Some(value) => value.to_string(),
+                    None => "nothing".to_string(),
+                };
+                let path = json_path(error.path());
+                ConfigError::Invalid {
+                    file: layers.origin(&path).to_path_buf(),
+                    path,
+                    expected,
+                    found,
+                }
+            })?;
+        settings.validate(layers)?;
+        Ok(settings)
+    }
+
+    /// Expands every path-valued option in place, see `paths::resolve_path`.
+    pub fn resolve_paths(&mut self, config_dir: &Path) -> Result<(), ConfigError> {
+        let resolve = |key: String, value: &str| -> Result<String, ConfigError> {
+            Ok(resolve_path(&key, value, config_dir)?
+                .to_string_lossy()
+                .to_string())
+        };
+        self.stylesheet = resolve("$config.stylesheet".to_string(), &self.stylesheet)?;
+        self.wallpaper.path = resolve("$config.wallpaper.path".to_string(), &self.wallpaper.path)?;
+        for (i, shader) in self.shaders.iter_mut().enumerate() {
+            shader.path = resolve(format!("$config.shaders[{}].path", i), &shader.path)?;
+        }
+        for (key, sensor) in self.sensors.iter_mut() {
+            if let Some(path) = &sensor.path {
+                let key = format!("$config.sensors{}.path", key_segment(key));
+                sensor.path = Some(resolve(key, path)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self, layers: &Layers) -> Result<(), ConfigError> {
+        let invalid = |path: String, expected: &str, found: &str| ConfigError::Invalid {
+            file: layers.origin(&path).to_path_buf(),
+            path,
+            expected: expected.to_string(),
+            found: Value::String(found.to_string()).to_string(),
+        };
+        if self.bar.height <= 0 {
+            return Err(ConfigError::Invalid {
+                file: layers.origin("$config.bar.height").to_path_buf(),
+                path: "$config.bar.height".to_string(),
+                expected: "a height in pixels greater than 0".to_string(),
+                found: self.bar.height.to_string(),
+            });
+        }
+        if self.power.battery_fps == 0 || self.power.battery_fps > 30 {
+            return Err(ConfigError::Invalid {
+                file: layers.origin("$config.power.battery_fps").to_path_buf(),
+                path: "$config.power.battery_fps".to_string(),
+                expected: "frames per second from 1 to 30".to_string(),
+                found: self.power.battery_fps.to_string(),
+            });
+        }
+        if self.processes.count == 0 || self.processes.count > 50 {
+            return Err(ConfigError::Invalid {
+                file: layers.origin("$config.processes.count").to_path_buf(),
+                path: "$config.processes.count".to_string(),
+                expected: "a number of processes from 1 to 50".to_string(),
+                found: self.processes.count.to_string(),
+            });
+        }
+        let positive = [
+            ("network.peak_seconds", self.network.peak_seconds),
+            ("network.min_peak", self.network.min_peak),
+            ("disk.peak_seconds", self.disk.peak_seconds),
+            ("disk.min_peak", self.disk.min_peak),
+        ];
+        for (key, value) in positive {
+            if value <= 0.0 {
+                let path = format!("$config.{}", key);
+                return Err(ConfigError::Invalid {
+                    file: layers.origin(&path).to_path_buf(),
+                    path,
+                    expected: "a number greater than 0".to_string(),
+                    found: value.to_string(),
+                });
+            }
+        }
+        let lists = [
+            (
+                "network.graph",
+                &self.network.graph,
+                "an interface name or kind",
+            ),
+            (
+                "network.ignore",
+                &self.network.ignore,
+                "an interface name or kind",
+            ),
+            ("disk.mounts", &self.disk.mounts, "an absolute path"),
+            ("disk.devices", &self.disk.devices, "a block device name"),
+        ];
+        for (list, names, expected) in lists {
+            for (i, name) in names.iter().enumerate() {
+                let absolute = list != "disk.mounts" || name.starts_with('/');
+                if name.trim().is_empty() || !absolute {
+                    let path = format!("$config.{}[{}]", list, i);
+                    return Err(invalid(path, expected, name));
+                }
+            }
+        }
+        if self.stylesheet.trim().is_empty() {
+            return Err(invalid(
+                "$config.stylesheet".to_string(),
+                "a path to a css file",
+                &self.stylesheet,
+            ));
+        }
+        let zones = [
+            ("left", &self.modules.left),
+            ("center", &self.modules.center),
+            ("right", &self.modules.right),
+        ];
+        let mut seen: Vec<&String> = vec![];
+        for (zone, modules) in zones {
+            for (i, name) in modules.iter().enumerate() {
+                let path = format!("$config.modules.{}[{}]", zone, i);
+                if !is_module(name) {
+                    let expected = format!("one of {}, sensor:<name>", MODULES.join(", "));
+                    return Err(invalid(path, &expected, name));
+                }
+                if seen.contains(&name) {
+                    return Err(invalid(path, "a module that is not already placed", name));
+                }
+                seen.push(name);
+            }
+        }
+        for (i, shader) in self.shaders.iter().enumerate() {
+            if shader.name.trim().is_empty() {
+                return Err(invalid(
+                    format!("$config.shaders[{}].name", i),
+                    "a non-empty name",
+                    &shader.name,
+                ));
+            }
+            if shader.path.trim().is_empty() {
+                return Err(invalid(
+                    format!("$config.shaders[{}].path", i),
+                    "a path to a glsl file",
+                    &shader.path,
+                ));
+            }
+            let duplicate = self.shaders[..i].iter().any(|s| s.name == shader.name);
+            if duplicate {
+                return Err(invalid(
+                    format!("$config.shaders[{}].name", i),
+                    "a unique shader name",
+                    &shader.name,
+                ));
+            }
+        }
+        for (key, sensor) in &self.sensors {
+            let path = |field: &str| format!("$config.sensors{}.{}", key_segment(key), field);
+            if let (Some(min), Some(max)) = (sensor.min, sensor.max) {
+                if min >= max {
+                    return Err(ConfigError::Invalid {
+                        file: layers.origin(&path("max")).to_path_buf(),
+                        path: path("max"),
+                        expected: format!("a value greater than min ({})", min),
+                        found: max.to_string(),
+                    });
+                }
+            }
+            if sensor.path.as_ref().is_some_and(|p| p.trim().is_empty()) {
+                return Err(invalid(path("path"), "a path to a sysfs file", ""));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn json_path(path: &serde_path_to_error::Path) -> String {
+    let mut out = "$config".to_string();
+    for segment in path {
+        match segment {
+            Segment::Seq { index } => out.push_str(&format!("[{}]", index)),
+            Segment::Map { key } => out.push_str(&key_segment(key)),
+            Segment::Enum { variant } => out.push_str(&format!(".{}", variant)),
+            Segment::Unknown => out.push_str(".?"),
+        }
+    }
+    out
+}
+
+fn lookup<'a>(root: &'a Value, path: &serde_path_to_error::Path) -> Option<&'a Value> {
+    let mut value = root;
+    for segment in path {
+        value = match segment {
+            Segment::Seq { index } => value.get(*index)?,
+            Segment::Map { key } => value.get(key)?,
+            Segment::Enum { .. } | Segment::Unknown => return Some(value),
+        };
+    }
+    Some(value)
+}
+
+/*
+ ███████╗██████╗ ██████╗  ██████╗ ██████╗
+ ██╔════╝██╔══██╗██╔══██╗██╔═══██╗██╔══██╗
+ █████╗  ██████╔╝██████╔╝██║   ██║██████╔╝
+ ██╔══╝  ██╔══██╗██╔══██╗██║   ██║██╔══██╗
+ ███████╗██║  ██║██║  ██║╚██████╔╝██║  ██║
+ ╚══════╝╚═╝  ╚═╝╚═╝  ╚═╝ ╚═════╝ ╚═╝  ╚═╝
+*/
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        file: PathBuf,
+        error: std::io::Error,
+    },
+    Syntax {
+        file: PathBuf,
+        error: String,
+    },
+    Invalid {
+        file: PathBuf,
+        path: String,
+        expected: String,
+        found: String,
+    },
+    Unresolved {
+        path: String,
+        value: String,
+        placeholder: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { file, error } => write!(f, "{}: {}", file.display(), error),
+            ConfigError::Syntax { file, error } => write!(f, "{}: {}", file.display(), error),
+            ConfigError::Invalid {
+                file,
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: {}: expected {}, found {}",
+                file.display(),
+                path,
+                expected,
+                found
+            ),
+            ConfigError::Unresolved {
+                path,
+                value,
+                placeholder,
+                reason,
+            } => write!(
+                f,
+                "{}: can not resolve {} in {:?}: {}",
+                path, placeholder, value, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
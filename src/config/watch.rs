@@ -0,0 +1,72 @@
+use crate::utils::{early_return, global};
+use colored::Colorize;
+use glib::spawn_future_local;
+use gtk4::{gio, glib, prelude::*};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/*
+ ██╗    ██╗ █████╗ ████████╗ ██████╗██╗  ██╗
+ ██║    ██║██╔══██╗╚══██╔══╝██╔════╝██║  ██║
+ ██║ █╗ ██║███████║   ██║   ██║     ███████║
+ ██║███╗██║██╔══██║   ██║   ██║     ██╔══██║
+ ╚███╔███╔╝██║  ██║   ██║   ╚██████╗██║  ██║
+  ╚══╝╚══╝ ╚═╝  ╚═╝   ╚═╝    ╚═════╝╚═╝  ╚═╝
+
+    Editors tend to save in bursts (write, chmod, rename, ...), so changes
+    are collected for a short while and reported as a single reload.
+*/
+
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+global!(monitors, Vec<(PathBuf, gio::FileMonitor)>, Vec::new());
+global!(reload_pending, bool, false);
+
+/// Watching the same directory twice is a no-op.
+pub fn watch_config(dir: &Path, on_change: fn()) {
+    early_return!(monitors().iter().any(|(watched, _)| watched == dir));
+    let monitor = gio::File::for_path(dir)
+        .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE);
+    let monitor = match monitor {
+        Ok(monitor) => monitor,
+        Err(err) => {
+            eprintln!(
+                "[{}]: {}({}): {}",
+                "config".green(),
+                "watch".yellow(),
+                dir.display(),
+                err.to_string().red()
+            );
+            return;
+        }
+    };
+    monitor.connect_changed(move |_, file, _, event| {
+        early_return!(event == gio::FileMonitorEvent::Attribute);
+        eprintln!(
+            "[{}]: {}({:?})",
+            "config".green(),
+            "changed".yellow(),
+            file.path().unwrap_or_default()
+        );
+        schedule_reload(on_change);
+    });
+    eprintln!(
+        "[{}]: {}({})",
+        "config".green(),
+        "watch".yellow(),
+        dir.display()
+    );
+    monitors().push((dir.to_path_buf(), monitor));
+}
+
+fn schedule_reload(on_change: fn()) {
+    early_return!(*reload_pending());
+    *reload_pending() = true;
+    spawn_future_local(async move {
+        glib::timeout_future(DEBOUNCE).await;
+        *reload_pending() = false;
+        on_change();
+    });
+}
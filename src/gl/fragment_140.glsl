@@ -13,19 +13,25 @@ out vec4 f_color;
 */
 
 float read_pixel(uint index, uint ptr);
+bool is_available(uint mask, uint index);
 ivec3 u32d3(uint data);
 ivec4 u32d4(uint data);
 vec3 u32color(uint data);
 vec4 bar_history_pixel(inout vec4 O, vec2 U, uint bar_index);
 vec4 bar_pixel(inout vec4 O, vec2 U, uint bar_index);
 vec4 bar(inout vec4 O, vec2 U);
+vec4 power_flash(vec4 O);
 vec4 draw_icon(vec4 O, vec2 U);
 uint align_char(uint char);
 vec4 gague_circle(inout vec4 O, vec2 U, uint gauge_index);
+uint gauge_value_at(uint gauge_index);
+bool gauge_available_at(uint gauge_index);
+vec3 gauge_color_at(uint gauge_index);
 void gague(inout vec4 O, vec2 uv, vec2 center, int radius, int line_width, vec3 color, float angle);
 
 uniform sensors {
   uint width;
+  uint height;
   uint gauge_count;
   uint gauge_value[6];
   uint gauge_color[6];
@@ -33,6 +39,27 @@ uniform sensors {
   uint load_count;
   uint load_color[24];
   uint load[2048];
+  uint gauge_available;
+  uint load_available;
+  // power, rate (128 = 0 W), time to empty, time to full, health
+  uint battery[5];
+  uint battery_available;
+  // 0 unknown, 1 ac, 2 battery
+  uint power_source;
+  // ms since the last plug or unplug, saturates
+  uint power_changed;
+  // usage of the first mount, the busiest disk, read, write
+  uint disk[4];
+  uint disk_available;
+  // used, apps, cache, shared, free, swap, zram used, zram ratio, session
+  uint memory[9];
+  uint memory_available;
+  // usage, user, system, iowait, steal, fastest core (scaled), governor, epp, throttle count
+  uint cpu[9];
+  uint cpu_available;
+  // busiest engine, render, video, compute, the busiest GPU each
+  uint gpu[4];
+  uint gpu_available;
 };
 
 uniform sampler2D font;
@@ -63,7 +90,7 @@ float bar_height = 24. / 16.;
 
 uint gauge_radius = 9u;
 uint gauge_dist = 28u;
-uint gauge_groups = 3u;
+uint gauge_groups = 4u;
 uint gauge_space = 4u;
 
 vec4 gague(inout vec4 O, vec2 U) {
@@ -82,14 +109,19 @@ vec4 gague(inout vec4 O, vec2 U) {
 
 vec4 gague_circle(inout vec4 O, vec2 U, uint gauge_index) {
   float gauge_index_f = float(gauge_index);
-  vec3 color = u32d3(gauge_color[gauge_index]);
+  vec3 color = gauge_color_at(gauge_index);
   float red = color.r / 255.0;
   float green = color.g / 255.0;
   float blue = color.b / 255.0;
-  float angle = (TAU / 255) * gauge_value[gauge_index];
+  float angle = (TAU / 255) * gauge_value_at(gauge_index);
+  if(!gauge_available_at(gauge_index)) {
+    // missing sensors are drawn as a dim full ring
+    red = green = blue = 0.3;
+    angle = TAU;
+  }
   int line_width = gauge_index % 2u == 0u ? 5 : 1;
   int radius = gauge_index % 2u == 0u ? 7 : 4;
-  vec2 center = vec2(float(width - gauge_dist * uint(floor(gauge_index_f / 2.)) - gauge_dist / 2u), 11.5);
+  vec2 center = vec2(float(width - gauge_dist * uint(floor(gauge_index_f / 2.)) - gauge_dist / 2u), float(height) / 2. - .5);
   vec2 pos = U - center;
   float dist = length(pos);
   float lw2 = float(line_width) / 2.0;
@@ -110,6 +142,30 @@ vec4 gague_circle(inout vec4 O, vec2 U, uint gauge_index) {
   return mix(O, finalColor, finalColor.a);
 }
 
+// gauges 6 and 7 are the disk, usage outside and the busiest disk inside
+uint gauge_value_at(uint gauge_index) {
+  return gauge_index < 6u ? gauge_value[gauge_index] : disk[gauge_index - 6u];
+}
+
+bool gauge_available_at(uint gauge_index) {
+  if(gauge_index < 6u)
+    return is_available(gauge_available, gauge_index);
+  return is_available(disk_available, gauge_index - 6u);
+}
+
+vec3 gauge_color_at(uint gauge_index) {
+  if(gauge_index < 6u)
+    return vec3(u32d3(gauge_color[gauge_index]));
+  if(gauge_index == 7u)
+    return vec3(255.0);
+  // blue, orange above 80% and red above 90%
+  if(disk[0] > 230u)
+    return vec3(255.0, 0.0, 0.0);
+  if(disk[0] > 204u)
+    return vec3(255.0, 100.0, 0.0);
+  return vec3(0.0, 128.0, 255.0);
+}
+
 /*
  ██████╗  █████╗ ██████╗
  ██╔══██╗██╔══██╗██╔══██╗
@@ -122,8 +178,8 @@ vec4 gague_circle(inout vec4 O, vec2 U, uint gauge_index) {
 vec4 bar(inout vec4 O, vec2 U) {
   bool is_not_bar = U.x > bar_max + 256.0; //|| U.y > load_count;
   bool is_bar_pixel = U.x <= bar_max;
-  uint bar_index = uint(U.y * (load_count / 24.0));
-  if(is_not_bar)
+  uint bar_index = uint(U.y * (load_count / float(height)));
+  if(is_not_bar || !is_available(load_available, bar_index))
     return O;
   if(is_bar_pixel)
     return bar_pixel(O, U, bar_index);
@@ -145,7 +201,28 @@ vec4 bar_history_pixel(inout vec4 O, vec2 U, uint bar_index) {
   uint index = uint(U.x - bar_max);
   float value = read_pixel(bar_index, (256u + load_ptr - uint(index)) % 256u);
   float fade = 1. - float(index) / 256.;
-  return mix(O, vec4(U.x / 256., U.y / 24., 1., 1.), value * fade * bar_dim);
+  return mix(O, vec4(U.x / 256., U.y / float(height), 1., 1.), value * fade * bar_dim);
+}
+
+/*
+ ███████╗██╗      █████╗ ███████╗██╗  ██╗
+ ██╔════╝██║     ██╔══██╗██╔════╝██║  ██║
+ █████╗  ██║     ███████║███████╗███████║
+ ██╔══╝  ██║     ██╔══██║╚════██║██╔══██║
+ ██║     ███████╗██║  ██║███████║██║  ██║
+ ╚═╝     ╚══════╝╚═╝  ╚═╝╚══════╝╚═╝  ╚═╝
+
+    Plugging in flashes green, unplugging orange.
+*/
+
+uint flash_ms = 600u;
+
+vec4 power_flash(vec4 O) {
+  if(power_changed >= flash_ms)
+    return O;
+  float fade = 1.0 - float(power_changed) / float(flash_ms);
+  vec3 color = power_source == 1u ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.4, 0.0);
+  return mix(O, vec4(color, 1.0), 0.5 * fade);
 }
 
 /*
@@ -162,6 +239,7 @@ void main() {
   vec4 O = vec4(0.0, 0.0, 0.0, 0.0);
   O = bar(O, U);
   O = gague(O, U);
+  O = power_flash(O);
   f_color = O;
   return;
 }
@@ -191,3 +269,7 @@ float read_pixel(uint index, uint ptr) {
   ivec4 page = u32d4(load[int(index * 64u + floor(float(ptr) / 4.0))]);
   return float(page[ptr % 4u]) / 255.0;
 }
+
+bool is_available(uint mask, uint index) {
+  return index < 32u && ((mask >> index) & 1u) == 1u;
+}
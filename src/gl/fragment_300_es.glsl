@@ -14,19 +14,25 @@ out vec4 f_color;
 */
 
 float read_pixel(uint index, uint ptr);
+bool is_available(uint mask, uint index);
 ivec3 u32d3(uint data);
 ivec4 u32d4(uint data);
 vec3 u32color(uint data);
 vec4 bar_history_pixel(inout vec4 O, vec2 U, uint bar_index);
 vec4 bar_pixel(inout vec4 O, vec2 U, uint bar_index);
 vec4 bar(inout vec4 O, vec2 U);
+vec4 power_flash(vec4 O);
 vec4 draw_icon(vec4 O, vec2 U);
 uint align_char(uint char);
 vec4 gague_circle(inout vec4 O, vec2 U, uint gauge_index);
+uint gauge_value_at(uint gauge_index);
+bool gauge_available_at(uint gauge_index);
+vec3 gauge_color_at(uint gauge_index);
 void gague(inout vec4 O, vec2 uv, vec2 center, int radius, int line_width, vec3 color, float angle);
 
 uniform sensors {
   uint width;
+  uint height;
   uint time;
   uint gauge_count;
   uint gauge_value[6];
@@ -36,6 +42,27 @@ uniform sensors {
   uint load_color[24];
   uint load[2048];
   uint text[256];
+  uint gauge_available;
+  uint load_available;
+  // power, rate (128 = 0 W), time to empty, time to full, health
+  uint battery[5];
+  uint battery_available;
+  // 0 unknown, 1 ac, 2 battery
+  uint power_source;
+  // ms since the last plug or unplug, saturates
+  uint power_changed;
+  // usage of the first mount, the busiest disk, read, write
+  uint disk[4];
+  uint disk_available;
+  // used, apps, cache, shared, free, swap, zram used, zram ratio, session
+  uint memory[9];
+  uint memory_available;
+  // usage, user, system, iowait, steal, fastest core (scaled), governor, epp, throttle count
+  uint cpu[9];
+  uint cpu_available;
+  // busiest engine, render, video, compute, the busiest GPU each
+  uint gpu[4];
+  uint gpu_available;
 };
 
 uniform sampler2D font;
@@ -66,7 +93,7 @@ float bar_height = 24.f / 16.f;
 
 uint gauge_radius = 9u;
 uint gauge_dist = 28u;
-uint gauge_groups = 3u;
+uint gauge_groups = 4u;
 uint gauge_space = 4u;
 
 vec4 gague(inout vec4 O, vec2 U) {
@@ -85,14 +112,19 @@ vec4 gague(inout vec4 O, vec2 U) {
 
 vec4 gague_circle(inout vec4 O, vec2 U, uint gauge_index) {
   float gauge_index_f = float(gauge_index);
-  vec3 color = vec3(u32d3(gauge_color[gauge_index]));
+  vec3 color = gauge_color_at(gauge_index);
   float red = color.r / 255.0f;
   float green = color.g / 255.0f;
   float blue = color.b / 255.0f;
-  float angle = (TAU / 255.f) * float(gauge_value[gauge_index]);
+  float angle = (TAU / 255.f) * float(gauge_value_at(gauge_index));
+  if(!gauge_available_at(gauge_index)) {
+    // missing sensors are drawn as a dim full ring
+    red = green = blue = 0.3f;
+    angle = TAU;
+  }
   int line_width = gauge_index % 2u == 0u ? 5 : 1;
   int radius = gauge_index % 2u == 0u ? 7 : 4;
-  vec2 center = vec2(float(width - gauge_dist * uint(floor(gauge_index_f / 2.f)) - gauge_dist / 2u), 11.5f);
+  vec2 center = vec2(float(width - gauge_dist * uint(floor(gauge_index_f / 2.f)) - gauge_dist / 2u), float(height) / 2.f - .5f);
   vec2 pos = U - center;
   float dist = length(pos);
   float lw2 = float(line_width) / 2.0f;
@@ -113,6 +145,30 @@ vec4 gague_circle(inout vec4 O, vec2 U, uint gauge_index) {
   return mix(O, finalColor, finalColor.a);
 }
 
+// gauges 6 and 7 are the disk, usage outside and the busiest disk inside
+uint gauge_value_at(uint gauge_index) {
+  return gauge_index < 6u ? gauge_value[gauge_index] : disk[gauge_index - 6u];
+}
+
+bool gauge_available_at(uint gauge_index) {
+  if(gauge_index < 6u)
+    return is_available(gauge_available, gauge_index);
+  return is_available(disk_available, gauge_index - 6u);
+}
+
+vec3 gauge_color_at(uint gauge_index) {
+  if(gauge_index < 6u)
+    return vec3(u32d3(gauge_color[gauge_index]));
+  if(gauge_index == 7u)
+    return vec3(255.0f);
+  // blue, orange above 80% and red above 90%
+  if(disk[0] > 230u)
+    return vec3(255.0f, 0.0f, 0.0f);
+  if(disk[0] > 204u)
+    return vec3(255.0f, 100.0f, 0.0f);
+  return vec3(0.0f, 128.0f, 255.0f);
+}
+
 /*
  ██████╗  █████╗ ██████╗
  ██╔══██╗██╔══██╗██╔══██╗
@@ -125,8 +181,8 @@ vec4 gague_circle(inout vec4 O, vec2 U, uint gauge_index) {
 vec4 bar(inout vec4 O, vec2 U) {
   bool is_not_bar = U.x > bar_max + 256.0f; //|| U.y > load_count;
   bool is_bar_pixel = U.x <= bar_max;
-  uint bar_index = uint(U.y) * uint(float(load_count) / 24.f);
-  if(is_not_bar)
+  uint bar_index = uint(U.y) * uint(float(load_count) / float(height));
+  if(is_not_bar || !is_available(load_available, bar_index))
     return O;
   if(is_bar_pixel)
     return bar_pixel(O, U, bar_index);
@@ -148,7 +204,7 @@ vec4 bar_history_pixel(inout vec4 O, vec2 U, uint bar_index) {
   uint index = uint(U.x - bar_max);
   float value = read_pixel(bar_index, (256u + load_ptr - uint(index)) % 256u);
   float fade = 1.f - float(index) / 256.f;
-  return mix(O, vec4(U.x / 256.f, U.y / 24.f, 1.f, 1.f), value * fade * bar_dim);
+  return mix(O, vec4(U.x / 256.f, U.y / float(height), 1.f, 1.f), value * fade * bar_dim);
 }
 
 /*
@@ -164,8 +220,6 @@ uint text_start = 263u;
 float char_width = 7.0f;
 float char_height = 16.0f;
 
-ivec2 bar_size = ivec2(1920, 24);
-
 //float(char_width);
 //float(char_height);
 
@@ -175,6 +229,7 @@ uint chars_per_row = 36u;
 
 vec4 draw_text(vec4 O, vec2 U) {
   ivec3 time = u32d3(time);
+  ivec2 bar_size = ivec2(int(width), int(height));
   ivec2 Ui = ivec2(U);
   ivec2 bar_padding = ivec2(0, (float(bar_size.y) - char_height) / 2.f);
 
@@ -206,6 +261,27 @@ uint align_char(uint char) {
   return char - 33u;
 }
 
+/*
+ ███████╗██╗      █████╗ ███████╗██╗  ██╗
+ ██╔════╝██║     ██╔══██╗██╔════╝██║  ██║
+ █████╗  ██║     ███████║███████╗███████║
+ ██╔══╝  ██║     ██╔══██║╚════██║██╔══██║
+ ██║     ███████╗██║  ██║███████║██║  ██║
+ ╚═╝     ╚══════╝╚═╝  ╚═╝╚══════╝╚═╝  ╚═╝
+
+    Plugging in flashes green, unplugging orange.
+*/
+
+uint flash_ms = 600u;
+
+vec4 power_flash(vec4 O) {
+  if(power_changed >= flash_ms)
+    return O;
+  float fade = 1.0f - float(power_changed) / float(flash_ms);
+  vec3 color = power_source == 1u ? vec3(0.0f, 1.0f, 0.0f) : vec3(1.0f, 0.4f, 0.0f);
+  return mix(O, vec4(color, 1.0f), 0.5f * fade);
+}
+
 /*
  ███╗   ███╗ █████╗ ██╗███╗   ██╗
  ████╗ ████║██╔══██╗██║████╗  ██║
@@ -221,6 +297,7 @@ void main() {
   O = bar(O, U);
   O = gague(O, U);
   O = draw_text(O, U);
+  O = power_flash(O);
   f_color = O;
   return;
 }
@@ -250,3 +327,7 @@ float read_pixel(uint index, uint ptr) {
   ivec4 page = u32d4(load[int(index * 64u + uint(floor(float(ptr) / 4.0f)))]);
   return float(page[ptr % 4u]) / 255.0f;
 }
+
+bool is_available(uint mask, uint index) {
+  return index < 32u && ((mask >> index) & 1u) == 1u;
+}
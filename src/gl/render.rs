@@ -1,5 +1,6 @@
 use super::uniform::initialize_uniforms;
-use crate::gl::tools::read_shader;
+use crate::config::config;
+use crate::gl::tools::fragment_shader;
 use crate::gl::uniform::{default_index_buffer, default_vertex_buffer, SensorValues, Vertex};
 use glib::Propagation;
 use glium::backend::Context as GliumContext;
@@ -22,7 +23,7 @@ pub struct Renderer {
 impl Renderer {
     fn new(context: Rc<GliumContext>) -> Self {
         let vertex = include_str!("./vertex.glsl");
-        let fragment = include_str!("./fragment_140.glsl");
+        let fragment = fragment_shader(&config().settings.shaders);
         let index = default_index_buffer(&context);
         let triangles = default_vertex_buffer(&context);
 
@@ -57,6 +58,7 @@ impl Renderer {
         {
             let mut map = self.buffer.map();
             map.width = dimensions.0;
+            map.height = dimensions.1;
         }
         {
             frame
@@ -100,31 +102,24 @@ impl WidgetImpl for GliumGLArea {
         if widget.as_ref().error().is_some() {
             return;
         }
-        unsafe {
-            let context = GliumContext::new(widget.clone(), true, Default::default()).unwrap();
-            RENDERER = Some(Renderer::new(context));
-        }
+        let context = unsafe { GliumContext::new(widget.clone(), true, Default::default()) };
+        self.renderer.replace(Some(Renderer::new(context.unwrap())));
     }
 
     fn unrealize(&self) {
+        // the buffers have to go while their context is still alive
+        GLAreaExt::make_current(&*self.obj());
+        self.renderer.replace(None);
         self.parent_unrealize();
     }
 }
 
 impl GLAreaImpl for GliumGLArea {
     fn render(&self, _context: &GLContext) -> Propagation {
-        let renderer = renderer();
-        if renderer.is_some() {
-            renderer.unwrap().draw();
-        } else {
-            eprintln!("Renderer not initialized");
+        match self.renderer.borrow_mut().as_mut() {
+            Some(renderer) => renderer.draw(),
+            None => eprintln!("Renderer not initialized"),
         }
         Propagation::Stop
     }
 }
-
-static mut RENDERER: Option<Renderer> = None;
-
-pub fn renderer() -> Option<&'static mut Renderer> {
-    unsafe { return RENDERER.as_mut() }
-}
@@ -1,8 +1,36 @@
-pub fn read_shader(path: &str) -> String {
+use crate::config::ShaderSettings;
+use colored::Colorize;
+
+pub fn read_shader(path: &str) -> Result<String, std::io::Error> {
     use std::fs::File;
     use std::io::Read;
-    let mut file = File::open(path).unwrap();
+    let mut file = File::open(path)?;
     let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
-    contents
+    file.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+pub fn fragment_shader(shaders: &[ShaderSettings]) -> String {
+    for shader in shaders {
+        match read_shader(&shader.path) {
+            Ok(source) => {
+                eprintln!(
+                    "[{}]: {}({})",
+                    "shader".green(),
+                    "loaded".yellow(),
+                    shader.name
+                );
+                return source;
+            }
+            Err(err) => eprintln!(
+                "[{}]: {}({}): {} {}",
+                "shader".green(),
+                "skipped".yellow(),
+                shader.name,
+                shader.path,
+                err.to_string().red()
+            ),
+        }
+    }
+    include_str!("./fragment_140.glsl").to_string()
 }
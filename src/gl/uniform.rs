@@ -1,9 +1,12 @@
-use crate::sensors::sensors;
+use crate::config::config;
+use crate::sensors::power::{power, PowerSource};
+use crate::sensors::{registry, Registry};
 use glium::backend::Context;
 use glium::{
     buffer::Mapping, implement_uniform_block, implement_vertex, index::PrimitiveType, uniforms::*,
     IndexBuffer, VertexBuffer,
 };
+use gtk4::subclass::prelude::*;
 use std::rc::Rc;
 
 pub const HISTORY_SIZE: usize = 256;
@@ -12,6 +15,7 @@ pub const HISTORY_SIZE: usize = 256;
 #[derive(Clone, Copy)]
 pub struct SensorValues {
     pub width: u32,
+    pub height: u32,
     pub gauge_count: u32,
     pub gauge_value: [u32; 6],
     pub gauge_color: [u32; 6],
@@ -19,11 +23,41 @@ pub struct SensorValues {
     pub load_count: u32,
     pub load_color: [u32; 24],
     pub load: [u32; 2048],
+    /// bit n set: gauge n has a value
+    pub gauge_available: u32,
+    /// bit n set: load row n has a value
+    pub load_available: u32,
+    /// battery.power, .rate, .time_to_empty, .time_to_full and .health
+    pub battery: [u32; 5],
+    /// bit n set: battery[n] has a value
+    pub battery_available: u32,
+    /// 0 unknown, 1 ac, 2 battery
+    pub power_source: u32,
+    /// ms since the power source changed, u32::MAX before that or with $config.power.flash off
+    pub power_changed: u32,
+    /// disk.usage of the first mount, the busiest disk.busy, disk.read and disk.write
+    pub disk: [u32; 4],
+    /// bit n set: disk[n] has a value
+    pub disk_available: u32,
+    /// memory, the four of memory.breakdown, memory.swap, zram.used, zram.ratio and memory.session
+    pub memory: [u32; 9],
+    /// bit n set: memory[n] has a value
+    pub memory_available: u32,
+    /// cpu.usage, .user, .system, .iowait, .steal and the fastest cpu.freq, scaled;
+    /// then the cpu.governor and cpu.epp positions and the cpu.throttle total as they are
+    pub cpu: [u32; 9],
+    /// bit n set: cpu[n] has a value
+    pub cpu_available: u32,
+    /// the busiest gpu.busy, .render, .video and .compute
+    pub gpu: [u32; 4],
+    /// bit n set: gpu[n] has a value
+    pub gpu_available: u32,
 }
 
 implement_uniform_block!(
     SensorValues,
     width,
+    height,
     gauge_count,
     gauge_value,
     gauge_color,
@@ -31,6 +65,20 @@ implement_uniform_block!(
     load_count,
     load_color,
     load,
+    gauge_available,
+    load_available,
+    battery,
+    battery_available,
+    power_source,
+    power_changed,
+    disk,
+    disk_available,
+    memory,
+    memory_available,
+    cpu,
+    cpu_available,
+    gpu,
+    gpu_available,
 );
 
 #[inline]
@@ -59,81 +107,285 @@ const YELLOW: u32 = (255 as u32) << 24 | (255 as u32) << 16 | (0 as u32) << 8 |
 const GREEN: u32 = (0 as u32) << 24 | (255 as u32) << 16 | (0 as u32) << 8 | 0 as u32;
 const BLUE: u32 = (0 as u32) << 24 | (0 as u32) << 16 | (255 as u32) << 8 | 0 as u32;
 
+impl SensorValues {
+    pub fn new() -> Self {
+        SensorValues {
+            width: 1920,
+            height: 24,
+            gauge_count: 5,
+            gauge_value: [0u32; 6],
+            gauge_color: [RED, RED, BLUE, YELLOW, ORANGE, YELLOW],
+            load_ptr: 0,
+            load_count: load_count(registry().values("cpu.load").len()),
+            load_color: [u32e3(1, 1, 1); 24],
+            load: [0u32; 2048],
+            gauge_available: 0,
+            load_available: 0,
+            battery: [0u32; 5],
+            battery_available: 0,
+            power_source: 0,
+            power_changed: u32::MAX,
+            disk: [0u32; 4],
+            disk_available: 0,
+            memory: [0u32; 9],
+            memory_available: 0,
+            cpu: [0u32; 9],
+            cpu_available: 0,
+            gpu: [0u32; 4],
+            gpu_available: 0,
+        }
+    }
+}
+
 pub fn initialize_uniforms(context: Rc<Context>) -> UniformBuffer<SensorValues> {
     let mut buffer: UniformBuffer<SensorValues> = UniformBuffer::empty(&context.clone()).unwrap();
     {
-        let s = sensors();
         let mut map = buffer.map();
-        let frame = 0;
-        let ptr: usize = frame as usize % HISTORY_SIZE;
-        let cpus = s.cpu_count as usize;
-        map.width = 1920;
-        map.load = [0u32; 2048];
-        map.load_count = cpus as u32 + 2;
-        map.load_ptr = ptr as u32;
-        map.load_color = [u32e3(1, 1, 1); 24];
-        map.gauge_count = 5;
-        map.gauge_value = [0u32; 6];
-        map.gauge_color = [RED, RED, BLUE, YELLOW, ORANGE, YELLOW];
+        *map = SensorValues::new();
     }
 
     return buffer;
 }
 
 pub fn update_uniforms() {
-    let renderer = super::render::renderer();
-    if renderer.is_none() {
-        return;
+    for bar in crate::bar::bars().values() {
+        let mut renderer = bar.area.imp().renderer.borrow_mut();
+        let Some(renderer) = renderer.as_mut() else {
+            continue;
+        };
+        let mut map: Mapping<SensorValues> = renderer.buffer.map();
+        write_uniforms(&mut map, renderer.frame);
     }
-    let renderer = renderer.unwrap();
-    let mut map: Mapping<SensorValues> = renderer.buffer.map();
+}
 
-    let s = sensors();
-    let frame = renderer.frame;
-    let ptr: usize = frame as usize % HISTORY_SIZE;
+#[inline]
+pub fn write_pixel(map: &mut SensorValues, y: usize, x: usize, value: u8) {
+    let page_index = y * 64 + (x as f64 / 4.0).floor() as usize;
+    let byte_index = x % 4;
+    let mut page = u32d4(map.load[page_index]);
+    page[byte_index] = value;
+    map.load[page_index] = u32e4(page[0], page[1], page[2], page[3]);
+}
 
-    map.load_ptr = ptr as u32;
+#[inline]
+pub fn read_pixel(map: &SensorValues, y: usize, x: usize) -> u8 {
+    let page_index = y * 64 + (x as f64 / 4.0).floor() as usize;
+    u32d4(map.load[page_index])[x % 4]
+}
+
+/*
+ ██████╗  ██████╗ ██╗    ██╗███████╗
+ ██╔══██╗██╔═══██╗██║    ██║██╔════╝
+ ██████╔╝██║   ██║██║ █╗ ██║███████╗
+ ██╔══██╗██║   ██║██║███╗██║╚════██║
+ ██║  ██║╚██████╔╝╚███╔███╔╝███████║
+ ╚═╝  ╚═╝ ╚═════╝  ╚══╝╚══╝ ╚══════╝
+
+    The history rows after one row per core, top to bottom. Shaders find
+    a row by its place, so a new row goes at the end of `LOAD_ROWS` and
+    `load_count` follows from the list. Only 32 rows fit, with many cores
+    the last ones fall off.
+*/
 
-    #[inline]
-    fn write_pixel(map: &mut Mapping<SensorValues>, y: usize, x: usize, value: u8) {
-        let page_index = y * 64 + (x as f64 / 4.0).floor() as usize;
-        let byte_index = x % 4;
-        let mut page = u32d4(map.load[page_index]);
-        page[byte_index] = value;
-        map.load[page_index] = u32e4(page[0], page[1], page[2], page[3]);
+/// The rows `load_count` shows, the busiest device for the ones with several.
+pub const LOAD_ROWS: &[&str] = &[
+    "gpu.usage",
+    "cpu.temp",
+    "disk.busy",
+    "disk.io",
+    "pressure.cpu.some",
+    "pressure.memory.some",
+    "pressure.io.some",
+    "cpu.iowait",
+    "cpu.freq",
+];
+
+/// Written after `LOAD_ROWS` for shaders that want them, the gauges show these.
+pub const HIDDEN_ROWS: &[&str] = &["gpu.temp", "cpu.fan", "gpu.fan"];
+
+/// The cores and `LOAD_ROWS`.
+pub fn load_count(cpus: usize) -> u32 {
+    (cpus + LOAD_ROWS.len()).min(32) as u32
+}
+
+/// The scaled value of a row from `LOAD_ROWS` or `HIDDEN_ROWS`.
+pub fn load_row(r: &Registry, row: &str) -> Option<u8> {
+    // one value per disk or core, the busiest one speaks for all
+    let busiest = |name: &str| r.values(name).iter().copied().max();
+    match row {
+        // amdgpu has its own counter, the others only fdinfo
+        "gpu.usage" => r.value("gpu.usage").or(busiest("gpu.busy")),
+        "disk.io" => busiest("disk.read").max(busiest("disk.write")),
+        "disk.busy" | "cpu.freq" => busiest(row),
+        name => r.value(name),
     }
+}
+
+pub fn write_uniforms(map: &mut SensorValues, frame: u64) {
+    let r = registry();
+    let ptr: usize = frame as usize % HISTORY_SIZE;
+
+    map.load_ptr = ptr as u32;
 
-    for (i, usage) in s.cpu_load.iter().enumerate() {
-        write_pixel(&mut map, i, ptr, *usage);
+    let mut load_available: u32 = 0;
+    let cpu_load = r.values("cpu.load");
+    // 32 rows of history, the mask has a bit for each
+    for (i, usage) in cpu_load.iter().take(32).enumerate() {
+        write_pixel(map, i, ptr, *usage);
+        load_available |= 1 << i;
     }
 
-    let cpus: usize = s.cpu_count as usize;
+    let cpus: usize = cpu_load.len().min(32);
+    map.load_count = load_count(cpus);
 
-    if s.gpu_load_path != "" {
-        write_pixel(&mut map, cpus + 0 as usize, ptr, s.gpu_load[0] as u8);
+    // unavailable rows keep their history at 0, the mask hides them
+    let rows = LOAD_ROWS.iter().chain(HIDDEN_ROWS);
+    for (i, row) in rows.enumerate() {
+        if cpus + i >= 32 {
+            break;
+        }
+        let value = load_row(r, row);
+        write_pixel(map, cpus + i, ptr, value.unwrap_or(0));
+        if value.is_some() {
+            load_available |= 1 << (cpus + i);
+        }
     }
+    map.load_available = load_available;
 
-    write_pixel(&mut map, cpus + 1, ptr, s.cpu_temp as u8);
-    write_pixel(&mut map, cpus + 2, ptr, s.gpu_temp as u8);
-    write_pixel(&mut map, cpus + 3, ptr, s.cpu_fan as u8);
-    write_pixel(&mut map, cpus + 4, ptr, s.gpu_fan as u8);
+    // one value per disk, the busiest one speaks for all
+    let busiest = |name: &str| r.values(name).iter().copied().max();
 
-    map.gauge_value[0] = s.bat as u32;
-    map.gauge_value[1] = 255;
-    map.gauge_color[0] = match s.bat {
+    let battery = r.value("battery.capacity");
+    let charging = r.value("battery.charging");
+    let gauges = [
+        battery,
+        charging.map(|_| 255),
+        r.value("cpu.temp"),
+        r.value("cpu.fan"),
+        r.value("gpu.temp"),
+        r.value("gpu.fan"),
+    ];
+    map.gauge_available = 0;
+    for (i, value) in gauges.iter().enumerate() {
+        map.gauge_value[i] = value.unwrap_or(0) as u32;
+        if value.is_some() {
+            map.gauge_available |= 1 << i;
+        }
+    }
+    // the gauge value is scaled, 0..255 instead of 0..100
+    map.gauge_color[0] = match battery.unwrap_or(0) as u32 * 100 / 255 {
         0..=10 => RED,
         11..=20 => ORANGE,
         21..=30 => YELLOW,
         _ => GREEN,
     };
-    map.gauge_color[1] = match s.bat_status {
-        1 => GREEN,
+    map.gauge_color[1] = match charging {
+        Some(255) => GREEN,
         _ => RED,
     };
-    map.gauge_value[2] = s.cpu_temp as u32;
-    map.gauge_value[3] = s.cpu_fan as u32;
-    map.gauge_value[4] = s.gpu_temp as u32;
-    map.gauge_value[5] = s.gpu_fan as u32;
+
+    let battery_values = [
+        r.value("battery.power"),
+        r.value("battery.rate"),
+        r.value("battery.time_to_empty"),
+        r.value("battery.time_to_full"),
+        r.value("battery.health"),
+    ];
+    map.battery_available = 0;
+    for (i, value) in battery_values.iter().enumerate() {
+        map.battery[i] = value.unwrap_or(0) as u32;
+        if value.is_some() {
+            map.battery_available |= 1 << i;
+        }
+    }
+
+    let disk_values = [
+        r.value("disk.usage"),
+        busiest("disk.busy"),
+        busiest("disk.read"),
+        busiest("disk.write"),
+    ];
+    map.disk_available = 0;
+    for (i, value) in disk_values.iter().enumerate() {
+        map.disk[i] = value.unwrap_or(0) as u32;
+        if value.is_some() {
+            map.disk_available |= 1 << i;
+        }
+    }
+
+    // apps, cache, shared and free
+    let breakdown = r.values("memory.breakdown");
+    let part = |i: usize| breakdown.get(i).copied();
+    let memory_values = [
+        r.value("memory"),
+        part(0),
+        part(1),
+        part(2),
+        part(3),
+        r.value("memory.swap"),
+        r.value("zram.used"),
+        r.value("zram.ratio"),
+        r.value("memory.session"),
+    ];
+    map.memory_available = 0;
+    for (i, value) in memory_values.iter().enumerate() {
+        map.memory[i] = value.unwrap_or(0) as u32;
+        if value.is_some() {
+            map.memory_available |= 1 << i;
+        }
+    }
+
+    // names and counts are no use scaled
+    let raw = |name: &str| {
+        let entry = r.get(name).filter(|entry| entry.available)?;
+        let values = entry.reading.values();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() as u32)
+    };
+    let scaled = |name: &str| r.value(name).map(|value| value as u32);
+    let cpu_values = [
+        scaled("cpu.usage"),
+        scaled("cpu.user"),
+        scaled("cpu.system"),
+        scaled("cpu.iowait"),
+        scaled("cpu.steal"),
+        busiest("cpu.freq").map(|value| value as u32),
+        raw("cpu.governor"),
+        raw("cpu.epp"),
+        raw("cpu.throttle"),
+    ];
+    map.cpu_available = 0;
+    for (i, value) in cpu_values.iter().enumerate() {
+        map.cpu[i] = value.unwrap_or(0);
+        if value.is_some() {
+            map.cpu_available |= 1 << i;
+        }
+    }
+
+    // one value per GPU like the disks
+    let gpu_values = [
+        busiest("gpu.busy"),
+        busiest("gpu.render"),
+        busiest("gpu.video"),
+        busiest("gpu.compute"),
+    ];
+    map.gpu_available = 0;
+    for (i, value) in gpu_values.iter().enumerate() {
+        map.gpu[i] = value.unwrap_or(0) as u32;
+        if value.is_some() {
+            map.gpu_available |= 1 << i;
+        }
+    }
+
+    let power = power();
+    map.power_source = match power.source {
+        None => 0,
+        Some(PowerSource::Ac) => 1,
+        Some(PowerSource::Battery) => 2,
+    };
+    map.power_changed = match (power.changed_at, config().settings.power.flash) {
+        (Some(changed_at), true) => changed_at.elapsed().as_millis().min(u32::MAX as u128) as u32,
+        _ => u32::MAX,
+    };
 }
 
 /*
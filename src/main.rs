@@ -1,17 +1,19 @@
+mod bar;
+mod cli;
 mod config;
 mod gl;
+mod modules;
 mod sensors;
 mod tray;
 mod utils;
 mod wallpaper;
 
-use crate::tray::tray;
 use crate::wallpaper::init_wallpaper;
+use colored::Colorize;
 use config::config;
-use gl::*;
 use glib::spawn_future_local;
-use gtk4::{glib, prelude::*};
-use gtk4_layer_shell::LayerShell;
+use gtk4::{gio, glib, prelude::*};
+use sensors::power::PowerSource;
 use std::{ptr, time::Duration};
 use utils::global;
 
@@ -22,27 +24,18 @@ global!(
         .application_id("de.hakt0r.shaderbar")
         .build()
 );
-global!(
-    window,
-    gtk4::ApplicationWindow,
-    gtk4::ApplicationWindow::new(application())
-);
-global!(widget, GliumGLArea, GliumGLArea::default());
-global!(
-    widgets,
-    gtk4::Grid,
-    gtk4::Grid::builder()
-        .row_spacing(0)
-        .column_spacing(0)
-        .build()
-);
+global!(application_hold, Option<gio::ApplicationHoldGuard>, None);
 global!(is_ready, Option<bool>, Some(false));
 
 #[tokio::main]
 async fn main() -> glib::ExitCode {
+    if cli::args().command != cli::Command::Run {
+        cli::commands::run(cli::args().command.clone()).await;
+    }
+
     eprintln!("Starting shaderbar");
 
-    let config = config().await;
+    let config = config();
 
     pre_init().await;
 
@@ -53,157 +46,42 @@ async fn main() -> glib::ExitCode {
         post_init(&config);
     });
 
-    return application.run();
+    // the command line belongs to shaderbar, not to gtk
+    return application.run_with_args(&[env!("CARGO_PKG_NAME")]);
 }
 
 async fn pre_init() {
     load_epoxy();
     sensors::detect().await;
-    sensors::spawn_read_sensors();
-    sensors::spawn_read_sensors_lowfreq();
+    sensors::spawn_sample_sensors();
+    sensors::spawn_redetect_sensors();
+    sensors::spawn_watch_interfaces();
     render_timer();
 }
 
-fn init_ui(_: &gtk4::Application) {
-    let window = window();
-
-    window.init_layer_shell();
-    window.set_title(Some(env!("CARGO_PKG_NAME")));
-    window.set_layer(gtk4_layer_shell::Layer::Top);
-    window.set_namespace(env!("CARGO_PKG_NAME"));
-
-    window.auto_exclusive_zone_enable();
-    window.set_width_request(1920);
-    window.set_height_request(24);
-
-    window.set_margin(gtk4_layer_shell::Edge::Top, 0);
-    window.set_margin(gtk4_layer_shell::Edge::Right, 0);
-    window.set_margin(gtk4_layer_shell::Edge::Bottom, 0);
-    window.set_margin(gtk4_layer_shell::Edge::Left, 0);
-
-    window.set_anchor(gtk4_layer_shell::Edge::Top, true);
-    window.set_anchor(gtk4_layer_shell::Edge::Right, true);
-    window.set_anchor(gtk4_layer_shell::Edge::Left, true);
-    window.set_anchor(gtk4_layer_shell::Edge::Bottom, false);
-
-    let container = gtk4::Fixed::new();
-
-    window.set_child(Some(&container));
-
-    let widget = widget();
-    widget.set_width_request(1920);
-    widget.set_height_request(24);
-    container.put(widget, 0f64, 0f64);
-
-    let widgets = widgets();
-    widgets.attach(&tray::tray().widget, 0, 0, 1, 1);
-    container.put(widgets, 16f64, 0f64);
-    base_widgets();
-    window.present();
+fn init_ui(app: &gtk4::Application) {
+    // keep running while there is no monitor to put a bar on
+    application_hold().replace(app.hold());
+    bar::init_bars();
 
     (*is_ready()).replace(true);
 }
 
-fn base_widgets() {
-    date_time_widget();
-    user_host_widget();
-    window_name_widget();
+fn post_init(config: &config::Config) {
+    init_stylesheet();
+    init_wallpaper(config);
+    watch_config_dirs(config);
 }
 
-global!(
-    user_host,
-    (gtk4::Box, [gtk4::Label; 3]),
-    (
-        gtk4::Box::new(gtk4::Orientation::Horizontal, 0),
-        [
-            gtk4::Label::new(None),
-            gtk4::Label::new(None),
-            gtk4::Label::new(None)
-        ]
-    )
-);
-
-fn user_host_widget() {
-    fn update_user_host() {
-        let (_, [user, _, host]) = user_host();
-        user.set_text(whoami::username().as_str());
-        host.set_text(
-            whoami::fallible::hostname()
-                .unwrap_or("shaderbar".to_string())
-                .as_str(),
-        );
+/// config.d/ and hosts/ may show up later, so this runs again on every reload.
+fn watch_config_dirs(config: &config::Config) {
+    config::watch::watch_config(&config.config_dir, reload);
+    for dir in config::layer_dirs(&config.config_dir) {
+        config::watch::watch_config(&dir, reload);
+    }
+    if let Some(dir) = config.stylesheet_file.parent() {
+        config::watch::watch_config(dir, reload);
     }
-    spawn_future_local(async move {
-        let widgets = widgets();
-        let (container, [user, at, host]) = user_host();
-        container.add_css_class("user-host");
-        user.add_css_class("user");
-        at.add_css_class("at");
-        host.add_css_class("hostname");
-        at.set_text("@");
-        update_user_host();
-        widgets.attach(container, 1, 0, 1, 1);
-        loop {
-            update_user_host();
-            glib::timeout_future(Duration::from_millis(1000)).await;
-        }
-    });
-}
-
-global!(window_name, gtk4::Label, gtk4::Label::new(None));
-fn window_name_widget() {
-    spawn_future_local(async move {
-        let widgets = widgets();
-        let window_name = window_name();
-        window_name.set_text("swayfx");
-        window_name.add_css_class("window-name");
-        widgets.attach(window_name, 4, 0, 1, 1);
-        loop {
-            {
-                let window_script = "swaymsg -t get_tree | jq -r '.. | select(.type?) | select(.focused==true) | .window_properties.title '";
-                let window = std::process::Command::new("sh")
-                    .arg("-c")
-                    .arg(window_script)
-                    .output()
-                    .unwrap();
-                let utf8 = window.stdout;
-                let name = String::from_utf8(utf8)
-                    .to_owned()
-                    .unwrap()
-                    .trim()
-                    .to_string();
-                let name = match name.as_str() {
-                    "null" => "",
-                    "" => "",
-                    _ => name.as_str(),
-                };
-                window_name.set_text(name);
-            }
-            glib::timeout_future(Duration::from_millis(1000 / 3)).await;
-        }
-    });
-}
-
-global!(date_time, gtk4::Label, gtk4::Label::new(None));
-fn date_time_widget() {
-    spawn_future_local(async move {
-        let widgets = widgets();
-        let date_time = date_time();
-        date_time.set_text("00:00:00.00 1970/01/01");
-        date_time.add_css_class("date-time");
-        widgets.attach(date_time, 3, 0, 1, 1);
-        loop {
-            glib::timeout_future(Duration::from_millis(1000 / 30)).await;
-            let now = chrono::Local::now();
-            date_time.set_text(now.format("%T %Y/%m/%d").to_string().as_str());
-        }
-    });
-}
-
-fn post_init(config: &config::Config) {
-    spawn_future_local(init_stylesheet());
-    init_wallpaper(config);
-    tray();
 }
 
 fn load_epoxy() {
@@ -216,21 +94,58 @@ fn load_epoxy() {
     });
 }
 
-async fn init_stylesheet() {
-    let provider = gtk4::CssProvider::new();
-    #[cfg(not(debug_assertions))]
-    {
-        // PROD // PROD // PROD // PROD // PROD // PROD // PROD // PROD // PROD // PROD // PROD
-        let stylesheet_file = config().await.stylesheet_file.clone();
-        provider.load_from_path(&stylesheet_file);
-    } // PROD // PROD // PROD // PROD // PROD // PROD // PROD // PROD // PROD // PROD // PROD
-    #[cfg(debug_assertions)]
-    provider.load_from_path(std::path::Path::new("src/config/defaults.css"));
+global!(stylesheet, gtk4::CssProvider, gtk4::CssProvider::new());
+
+fn init_stylesheet() {
     gtk4::style_context_add_provider_for_display(
         &gtk4::gdk::Display::default().expect("Could not connect to a display."),
-        &provider,
+        stylesheet(),
         gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
     );
+    load_stylesheet();
+}
+
+fn load_stylesheet() {
+    let stylesheet_file = config().stylesheet_file.clone();
+    stylesheet().load_from_path(&stylesheet_file);
+}
+
+/*
+ ██████╗ ███████╗██╗      ██████╗  █████╗ ██████╗
+ ██╔══██╗██╔════╝██║     ██╔═══██╗██╔══██╗██╔══██╗
+ ██████╔╝█████╗  ██║     ██║   ██║███████║██║  ██║
+ ██╔══██╗██╔══╝  ██║     ██║   ██║██╔══██║██║  ██║
+ ██║  ██║███████╗███████╗╚██████╔╝██║  ██║██████╔╝
+ ╚═╝  ╚═╝╚══════╝╚══════╝ ╚═════╝ ╚═╝  ╚═╝╚═════╝
+
+    Only the sections that changed are re-applied, a stylesheet edit
+    just reloads the css. The tray client and the sensor history are
+    left untouched.
+*/
+
+fn reload() {
+    let before = config().settings.clone();
+    if let Err(err) = config::reload_config() {
+        eprintln!("[{}]: {}", "config".green(), err.to_string().red());
+        return;
+    }
+    let after = &config().settings;
+    watch_config_dirs(config());
+    load_stylesheet();
+    if before.sensors != after.sensors || before.disk != after.disk {
+        spawn_future_local(sensors::detect());
+    }
+    if before.wallpaper != after.wallpaper {
+        init_wallpaper(config());
+    }
+    if before.bar != after.bar {
+        // re-lays out the modules when bars come or go
+        bar::diff_bars();
+        bar::apply_geometry_all();
+    }
+    if before.modules != after.modules {
+        modules::apply_layout(&after.modules);
+    }
 }
 
 /*
@@ -246,8 +161,15 @@ fn render_timer() {
     spawn_future_local(async move {
         readyness().await;
         loop {
-            glib::timeout_future(Duration::from_millis(1000 / 30)).await;
-            widget().queue_render();
+            // the battery profile, fewer frames while unplugged
+            let fps = match sensors::power::power().source {
+                Some(PowerSource::Battery) => config().settings.power.battery_fps,
+                _ => 30,
+            };
+            glib::timeout_future(Duration::from_millis(1000 / fps as u64)).await;
+            for bar in bar::bars().values() {
+                bar.area.queue_render();
+            }
         }
     });
 }
@@ -0,0 +1,113 @@
+use super::is_current;
+use crate::config::{config, ModulesSettings};
+use crate::utils::{early_return, global};
+use glib::spawn_future_local;
+use gtk4::{gio, glib, prelude::*};
+use std::{ffi::OsStr, time::Duration};
+
+/*
+ ██████╗  █████╗ ███████╗███████╗
+ ██╔══██╗██╔══██╗██╔════╝██╔════╝
+ ██████╔╝███████║███████╗█████╗
+ ██╔══██╗██╔══██║╚════██║██╔══╝
+ ██████╔╝██║  ██║███████║███████╗
+ ╚═════╝ ╚═╝  ╚═╝╚══════╝╚══════╝
+*/
+
+pub fn user_host_widget(generation: u64) -> gtk4::Widget {
+    let container = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    let [user, at, host] = [
+        gtk4::Label::new(None),
+        gtk4::Label::new(Some("@")),
+        gtk4::Label::new(None),
+    ];
+    container.add_css_class("user-host");
+    user.add_css_class("user");
+    at.add_css_class("at");
+    host.add_css_class("hostname");
+    container.append(&user);
+    container.append(&at);
+    container.append(&host);
+    let update_user_host = move || {
+        user.set_text(whoami::username().as_str());
+        host.set_text(
+            whoami::fallible::hostname()
+                .unwrap_or("shaderbar".to_string())
+                .as_str(),
+        );
+    };
+    update_user_host();
+    spawn_future_local(async move {
+        while is_current(generation) {
+            update_user_host();
+            glib::timeout_future(Duration::from_millis(1000)).await;
+        }
+    });
+    container.upcast()
+}
+
+/// Every bar shows the same focused window, so one poller feeds them all.
+global!(focused_window, String, String::new());
+global!(window_poller, bool, false);
+
+const WINDOW_SCRIPT: &str = "swaymsg -t get_tree | jq -r '.. | select(.type?) | select(.focused==true) | .window_properties.title '";
+
+pub fn window_name_widget(generation: u64) -> gtk4::Widget {
+    let window_name = gtk4::Label::new(Some(focused_window().as_str()));
+    window_name.add_css_class("window-name");
+    let label = window_name.clone();
+    poll_window_name();
+    spawn_future_local(async move {
+        while is_current(generation) {
+            glib::timeout_future(Duration::from_millis(1000 / 3)).await;
+            label.set_text(focused_window());
+        }
+    });
+    window_name.upcast()
+}
+
+/// Runs as long as some bar lists `window-name`.
+fn poll_window_name() {
+    early_return!(*window_poller());
+    *window_poller() = true;
+    spawn_future_local(async move {
+        while lists_window_name(&config().settings.modules) {
+            *focused_window() = read_window_name().await.unwrap_or_default();
+            glib::timeout_future(Duration::from_millis(1000 / 3)).await;
+        }
+        *window_poller() = false;
+    });
+}
+
+fn lists_window_name(settings: &ModulesSettings) -> bool {
+    [&settings.left, &settings.center, &settings.right]
+        .iter()
+        .any(|zone| zone.iter().any(|name| name == "window-name"))
+}
+
+/// No sway, no jq or nothing focused all end up as an empty label.
+async fn read_window_name() -> Option<String> {
+    let argv = ["sh", "-c", WINDOW_SCRIPT].map(OsStr::new);
+    let flags = gio::SubprocessFlags::STDOUT_PIPE | gio::SubprocessFlags::STDERR_SILENCE;
+    let process = gio::Subprocess::newv(&argv, flags).ok()?;
+    let (stdout, _) = process.communicate_utf8_future(None).await.ok()?;
+    let name = stdout?.trim().to_string();
+    match name.as_str() {
+        "null" => None,
+        _ => Some(name),
+    }
+}
+
+pub fn date_time_widget(generation: u64) -> gtk4::Widget {
+    let date_time = gtk4::Label::new(Some("00:00:00.00 1970/01/01"));
+    date_time.add_css_class("date-time");
+    let label = date_time.clone();
+    spawn_future_local(async move {
+        while is_current(generation) {
+            glib::timeout_future(Duration::from_millis(1000 / 30)).await;
+            let now = chrono::Local::now();
+            label.set_text(now.format("%T %Y/%m/%d").to_string().as_str());
+        }
+    });
+    date_time.upcast()
+}
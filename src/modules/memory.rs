@@ -0,0 +1,76 @@
+use super::is_current;
+use crate::sensors::registry;
+use glib::spawn_future_local;
+use gtk4::{glib, prelude::*};
+use std::time::Duration;
+
+/*
+ ███╗   ███╗███████╗███╗   ███╗ ██████╗ ██████╗ ██╗   ██╗
+ ████╗ ████║██╔════╝████╗ ████║██╔═══██╗██╔══██╗╚██╗ ██╔╝
+ ██╔████╔██║█████╗  ██╔████╔██║██║   ██║██████╔╝ ╚████╔╝
+ ██║╚██╔╝██║██╔══╝  ██║╚██╔╝██║██║   ██║██╔══██╗  ╚██╔╝
+ ██║ ╚═╝ ██║███████╗██║ ╚═╝ ██║╚██████╔╝██║  ██║   ██║
+ ╚═╝     ╚═╝╚══════╝╚═╝     ╚═╝ ╚═════╝ ╚═╝  ╚═╝   ╚═╝
+
+    Memory in use (and swap, once there is some), the breakdown,
+    zram and the session cgroup in the tooltip.
+*/
+
+pub fn memory_widget(generation: u64) -> gtk4::Widget {
+    let label = gtk4::Label::new(None);
+    label.add_css_class("memory");
+    let widget = label.clone();
+    let update_memory = move || {
+        let Some(used) = reading("memory") else {
+            label.add_css_class("unavailable");
+            label.set_text("–");
+            return;
+        };
+        label.remove_css_class("unavailable");
+        let mut text = format!("mem {:.0}%", used[0]);
+        let swap = reading("memory.swap").map(|swap| swap[0]);
+        if let Some(swap) = swap.filter(|swap| *swap >= 1.0) {
+            text.push_str(&format!("  swap {:.0}%", swap));
+        }
+        label.set_text(&text);
+        label.set_tooltip_text(Some(&describe()));
+    };
+    update_memory();
+    spawn_future_local(async move {
+        while is_current(generation) {
+            glib::timeout_future(Duration::from_millis(1000)).await;
+            update_memory();
+        }
+    });
+    widget.upcast()
+}
+
+/// The raw values of an available sensor with at least one value.
+fn reading(name: &str) -> Option<Vec<f64>> {
+    let entry = registry().get(name).filter(|entry| entry.available)?;
+    Some(entry.reading.values()).filter(|values| !values.is_empty())
+}
+
+/// `apps 28%, cache 31%, shared 4%, free 36%` and the rest line by line.
+fn describe() -> String {
+    let mut lines = vec![];
+    if let Some(parts) = reading("memory.breakdown") {
+        let names = ["apps", "cache", "shared", "free"];
+        let parts = names
+            .iter()
+            .zip(parts)
+            .map(|(name, x)| format!("{} {:.0}%", name, x));
+        lines.push(parts.collect::<Vec<_>>().join(", "));
+    }
+    if let Some(swap) = reading("memory.swap") {
+        lines.push(format!("swap {:.0}%", swap[0]));
+    }
+    if let Some(used) = reading("zram.used") {
+        let ratio = reading("zram.ratio").map_or(String::new(), |x| format!(", {:.1}x", x[0]));
+        lines.push(format!("zram {:.1}% of RAM{}", used[0], ratio));
+    }
+    if let Some(session) = reading("memory.session") {
+        lines.push(format!("session {:.0}%", session[0]));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,119 @@
+mod base;
+mod memory;
+mod network;
+mod processes;
+mod sensor;
+
+use crate::bar::bars;
+use crate::config::ModulesSettings;
+use crate::tray::tray;
+use crate::utils::{early_return_value, global};
+use colored::Colorize;
+use gtk4::{gdk::Monitor, prelude::*, Orientation::Horizontal};
+
+/*
+ ███╗   ███╗ ██████╗ ██████╗ ██╗   ██╗██╗     ███████╗███████╗
+ ████╗ ████║██╔═══██╗██╔══██╗██║   ██║██║     ██╔════╝██╔════╝
+ ██╔████╔██║██║   ██║██║  ██║██║   ██║██║     █████╗  ███████╗
+ ██║╚██╔╝██║██║   ██║██║  ██║██║   ██║██║     ██╔══╝  ╚════██║
+ ██║ ╚═╝ ██║╚██████╔╝██████╔╝╚██████╔╝███████╗███████╗███████║
+ ╚═╝     ╚═╝ ╚═════╝ ╚═════╝  ╚═════╝ ╚══════╝╚══════╝╚══════╝
+
+    Each bar is split into three zones, every module listed in
+    $config.modules is created and polled, everything else is not.
+    Each layout bumps the generation, which ends the polling loops
+    of the previous layout.
+*/
+
+global!(generation, u64, 0);
+
+#[derive(Clone)]
+pub struct Zones {
+    pub container: gtk4::CenterBox,
+    pub boxes: [gtk4::Box; 3],
+}
+
+impl Zones {
+    pub fn new() -> Self {
+        let container = gtk4::CenterBox::new();
+        let boxes = [
+            gtk4::Box::new(Horizontal, 0),
+            gtk4::Box::new(Horizontal, 0),
+            gtk4::Box::new(Horizontal, 0),
+        ];
+        container.add_css_class("modules");
+        boxes[0].add_css_class("left");
+        boxes[1].add_css_class("center");
+        boxes[2].add_css_class("right");
+        container.set_start_widget(Some(&boxes[0]));
+        container.set_center_widget(Some(&boxes[1]));
+        container.set_end_widget(Some(&boxes[2]));
+        container.set_hexpand(true);
+        Zones { container, boxes }
+    }
+
+    pub fn clear(&self) {
+        for zone in &self.boxes {
+            while let Some(child) = zone.first_child() {
+                zone.remove(&child);
+            }
+        }
+    }
+}
+
+/// Every bar gets its own widgets, only the tray can not be duplicated
+/// and lives on the first bar (sorted by connector) that lists it.
+pub fn apply_layout(settings: &ModulesSettings) {
+    *generation() += 1;
+    let mut bars = bars().iter().collect::<Vec<_>>();
+    bars.sort_by_key(|(monitor, _)| monitor.connector().unwrap_or_default());
+    for (_, bar) in &bars {
+        bar.zones.clear();
+    }
+    for (monitor, bar) in bars {
+        let zones = &bar.zones;
+        zones.container.set_margin_start(settings.margin_left);
+        zones.container.set_margin_end(settings.margin_right);
+        let zone_modules = [&settings.left, &settings.center, &settings.right];
+        for (zone, modules) in zones.boxes.iter().zip(zone_modules) {
+            zone.set_spacing(settings.spacing);
+            for name in modules {
+                if let Some(widget) = create_module(name, monitor) {
+                    zone.append(&widget);
+                }
+            }
+        }
+    }
+}
+
+/// Polling loops call this to find out if their module is still part of the layout.
+pub fn is_current(generation_at_start: u64) -> bool {
+    *generation() == generation_at_start
+}
+
+fn create_module(name: &str, monitor: &Monitor) -> Option<gtk4::Widget> {
+    eprintln!(
+        "[{}]: {}({}) @{}",
+        "modules".green(),
+        "create".yellow(),
+        name,
+        monitor.connector().unwrap_or_default()
+    );
+    match name {
+        "tray" => {
+            let widget = &tray().widget;
+            early_return_value!(widget.parent().is_some(), None);
+            Some(widget.clone().upcast())
+        }
+        "user-host" => Some(base::user_host_widget(*generation())),
+        "date-time" => Some(base::date_time_widget(*generation())),
+        "window-name" => Some(base::window_name_widget(*generation())),
+        "network" => Some(network::network_widget(*generation())),
+        "memory" => Some(memory::memory_widget(*generation())),
+        "processes" => Some(processes::processes_widget(*generation())),
+        _ => {
+            let sensor = name.strip_prefix(sensor::PREFIX)?;
+            Some(sensor::sensor_widget(sensor, *generation()))
+        }
+    }
+}
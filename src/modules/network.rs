@@ -0,0 +1,72 @@
+use super::is_current;
+use crate::config::config;
+use crate::sensors::interfaces::{graphed, interfaces, Interface};
+use glib::spawn_future_local;
+use gtk4::{glib, prelude::*};
+use std::time::Duration;
+
+/*
+ ███╗   ██╗███████╗████████╗██╗    ██╗ ██████╗ ██████╗ ██╗  ██╗
+ ████╗  ██║██╔════╝╚══██╔══╝██║    ██║██╔═══██╗██╔══██╗██║ ██╔╝
+ ██╔██╗ ██║█████╗     ██║   ██║ █╗ ██║██║   ██║██████╔╝█████╔╝
+ ██║╚██╗██║██╔══╝     ██║   ██║███╗██║██║   ██║██╔══██╗██╔═██╗
+ ██║ ╚████║███████╗   ██║   ╚███╔███╔╝╚██████╔╝██║  ██║██║  ██╗
+ ╚═╝  ╚═══╝╚══════╝   ╚═╝    ╚══╝╚══╝  ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝
+
+    The graphed interfaces and their first address, every interface
+    with kind, state and speed in the tooltip.
+*/
+
+pub fn network_widget(generation: u64) -> gtk4::Widget {
+    let label = gtk4::Label::new(None);
+    label.add_css_class("network");
+    let widget = label.clone();
+    let update_network = move || {
+        let settings = &config().settings.network;
+        let interfaces = interfaces();
+        let shown = graphed(interfaces, settings)
+            .iter()
+            .map(|interface| match interface.ipv4.first() {
+                Some(addr) => format!("{} {}", interface.name, addr),
+                None => interface.name.clone(),
+            })
+            .collect::<Vec<_>>();
+        match shown.is_empty() {
+            true => {
+                label.add_css_class("offline");
+                label.set_text("offline");
+            }
+            false => {
+                label.remove_css_class("offline");
+                label.set_text(&shown.join("  "));
+            }
+        }
+        let details = interfaces.iter().map(describe).collect::<Vec<_>>();
+        label.set_tooltip_text(Some(&details.join("\n")));
+    };
+    update_network();
+    spawn_future_local(async move {
+        while is_current(generation) {
+            glib::timeout_future(Duration::from_millis(1000)).await;
+            update_network();
+        }
+    });
+    widget.upcast()
+}
+
+/// `enp5s0 (ethernet, up, 1000 Mbit/s) 192.168.178.20 fe80::3c4d:5eff:fe6a:7b8c`
+fn describe(interface: &Interface) -> String {
+    let mut state = vec![
+        interface.kind.name().to_string(),
+        interface.operstate.clone(),
+    ];
+    state.extend(interface.speed.map(|speed| format!("{} Mbit/s", speed)));
+    let addresses = interface.ipv4.iter().map(|addr| addr.to_string());
+    let addresses = addresses.chain(interface.ipv6.iter().map(|addr| addr.to_string()));
+    let mut line = format!("{} ({})", interface.name, state.join(", "));
+    for addr in addresses {
+        line.push(' ');
+        line.push_str(&addr);
+    }
+    line
+}
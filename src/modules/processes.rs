@@ -0,0 +1,97 @@
+use super::is_current;
+use crate::config::config;
+use crate::sensors::drm::gpu_usage;
+use crate::sensors::processes::{top_processes, Process};
+use glib::spawn_future_local;
+use gtk4::{glib, prelude::*};
+use std::time::Duration;
+
+/*
+ ██████╗ ██████╗  ██████╗  ██████╗███████╗███████╗███████╗███████╗███████╗
+ ██╔══██╗██╔══██╗██╔═══██╗██╔════╝██╔════╝██╔════╝██╔════╝██╔════╝██╔════╝
+ ██████╔╝██████╔╝██║   ██║██║     █████╗  ███████╗███████╗█████╗  ███████╗
+ ██╔═══╝ ██╔══██╗██║   ██║██║     ██╔══╝  ╚════██║╚════██║██╔══╝  ╚════██║
+ ██║     ██║  ██║╚██████╔╝╚██████╗███████╗███████║███████║███████╗███████║
+ ╚═╝     ╚═╝  ╚═╝ ╚═════╝  ╚═════╝╚══════╝╚══════╝╚══════╝╚══════╝╚══════╝
+
+    The busiest process in the bar, a click opens the top ones by cpu
+    and by memory (see $config.processes) with their command lines, and
+    the ones using the GPU if the `gpu.*` fdinfo sensors are running.
+*/
+
+/// Command lines get cut here, some run for pages.
+const CMDLINE_LENGTH: usize = 80;
+
+pub fn processes_widget(generation: u64) -> gtk4::Widget {
+    let label = gtk4::Label::new(None);
+    let list = gtk4::Label::new(None);
+    list.add_css_class("processes-list");
+    list.set_xalign(0.0);
+    let popover = gtk4::Popover::builder().child(&list).build();
+    let button = gtk4::MenuButton::builder()
+        .css_classes(["processes"])
+        .child(&label)
+        .popover(&popover)
+        .build();
+    let update_processes = move || {
+        let top = top_processes();
+        match top.by_cpu.first() {
+            Some(busiest) => {
+                label.remove_css_class("unavailable");
+                label.set_text(&format!("{} {:.0}%", busiest.comm, busiest.cpu));
+            }
+            None => {
+                label.add_css_class("unavailable");
+                label.set_text("–");
+            }
+        }
+        let mut lines = vec!["cpu".to_string()];
+        lines.extend(
+            top.by_cpu
+                .iter()
+                .map(|x| describe(x, format!("{:5.1}%", x.cpu))),
+        );
+        lines.push(String::new());
+        lines.push("memory".to_string());
+        let mib = |x: &Process| format!("{:4} MiB", x.rss / 1024 / 1024);
+        lines.extend(top.by_memory.iter().map(|x| describe(x, mib(x))));
+        let usage = gpu_usage();
+        let count = config().settings.processes.count;
+        let drawing = usage.processes.iter().filter(|x| x.busy > 0.0);
+        let gpu_lines = drawing.take(count).map(|x| {
+            // which GPU only matters with more than one
+            let gpu = match usage.gpus.len() {
+                1 => String::new(),
+                _ => format!("  gpu{}", x.gpu),
+            };
+            format!("{:5.1}%  {:>7}  {}{}", x.busy, x.pid, x.comm, gpu)
+        });
+        let gpu_lines = gpu_lines.collect::<Vec<_>>();
+        if !gpu_lines.is_empty() {
+            lines.push(String::new());
+            lines.push("gpu".to_string());
+            lines.extend(gpu_lines);
+        }
+        list.set_text(&lines.join("\n"));
+    };
+    update_processes();
+    spawn_future_local(async move {
+        while is_current(generation) {
+            glib::timeout_future(Duration::from_millis(2000)).await;
+            update_processes();
+        }
+    });
+    button.upcast()
+}
+
+/// ` 23.0%  1377  firefox  /usr/lib/firefox/firefox`, kernel threads in brackets.
+fn describe(process: &Process, value: String) -> String {
+    let command = match process.cmdline.is_empty() {
+        true => format!("[{}]", process.comm),
+        false => process.cmdline.chars().take(CMDLINE_LENGTH).collect(),
+    };
+    format!(
+        "{}  {:>7}  {}  {}",
+        value, process.pid, process.comm, command
+    )
+}
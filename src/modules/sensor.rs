@@ -0,0 +1,48 @@
+use super::is_current;
+use crate::sensors::registry;
+use glib::spawn_future_local;
+use gtk4::{glib, prelude::*};
+use std::time::Duration;
+
+/*
+ ███████╗███████╗███╗   ██╗███████╗ ██████╗ ██████╗
+ ██╔════╝██╔════╝████╗  ██║██╔════╝██╔═══██╗██╔══██╗
+ ███████╗█████╗  ██╔██╗ ██║███████╗██║   ██║██████╔╝
+ ╚════██║██╔══╝  ██║╚██╗██║╚════██║██║   ██║██╔══██╗
+ ███████║███████╗██║ ╚████║███████║╚██████╔╝██║  ██║
+ ╚══════╝╚══════╝╚═╝  ╚═══╝╚══════╝ ╚═════╝ ╚═╝  ╚═╝
+
+    `sensor:<name>` in $config.modules, the current value of one
+    registry sensor as text. Missing sensors get the `unavailable` class.
+*/
+
+pub const PREFIX: &str = "sensor:";
+
+pub fn sensor_widget(name: &str, generation: u64) -> gtk4::Widget {
+    let label = gtk4::Label::new(None);
+    label.add_css_class("sensor");
+    label.add_css_class(&name.replace('.', "-"));
+    let name = name.to_string();
+    let widget = label.clone();
+    let update_sensor = move || {
+        let value = registry().get(&name).and_then(|entry| entry.display());
+        match value {
+            Some(value) => {
+                label.remove_css_class("unavailable");
+                label.set_text(&value);
+            }
+            None => {
+                label.add_css_class("unavailable");
+                label.set_text("–");
+            }
+        }
+    };
+    update_sensor();
+    spawn_future_local(async move {
+        while is_current(generation) {
+            glib::timeout_future(Duration::from_millis(1000)).await;
+            update_sensor();
+        }
+    });
+    widget.upcast()
+}
@@ -0,0 +1,88 @@
+use super::{read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/*
+  █████╗  ██████╗
+ ██╔══██╗██╔════╝
+ ███████║██║
+ ██╔══██║██║
+ ██║  ██║╚██████╗
+ ╚═╝  ╚═╝ ╚═════╝
+
+    1 while any AC adapter is online. A configured path (a power supply
+    or a file in it) limits the sensor to that adapter.
+*/
+
+pub struct AcOnline {
+    adapters: Vec<PathBuf>,
+}
+
+impl AcOnline {
+    pub fn new() -> Self {
+        AcOnline { adapters: vec![] }
+    }
+
+    fn read(&self) -> Option<bool> {
+        let mut online = None;
+        for adapter in &self.adapters {
+            let Ok(text) = read_string_from_file_sync(adapter.join("online").to_str()?) else {
+                continue;
+            };
+            online = Some(online.unwrap_or(false) || text == "1");
+        }
+        online
+    }
+}
+
+impl Sensor for AcOnline {
+    fn name(&self) -> &str {
+        "ac.online"
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.adapters = match detected.configured(self.name()) {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                match path.is_file() {
+                    true => vec![path.parent().unwrap_or(&path).to_path_buf()],
+                    false => vec![path],
+                }
+            }
+            None => (0..detected.get("ac.count").map_or(0, |count| count.to_u64()))
+                .filter_map(|i| detected.get(&format!("ac[{}].path", i)))
+                .map(|path| PathBuf::from(path.to_string()))
+                .collect(),
+        };
+        self.read().is_some()
+    }
+
+    fn sample(&mut self) -> Reading {
+        match self.read() {
+            Some(online) => Reading::Value(online as u8 as f64),
+            None => Reading::Unavailable,
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Raw
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 1.0)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn source(&self) -> String {
+        let adapters = self
+            .adapters
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>();
+        adapters.join(", ")
+    }
+}
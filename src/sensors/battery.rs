@@ -0,0 +1,246 @@
+use super::{read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/*
+ ██████╗  █████╗ ████████╗████████╗███████╗██████╗ ██╗   ██╗
+ ██╔══██╗██╔══██╗╚══██╔══╝╚══██╔══╝██╔════╝██╔══██╗╚██╗ ██╔╝
+ ██████╔╝███████║   ██║      ██║   █████╗  ██████╔╝ ╚████╔╝
+ ██╔══██╗██╔══██║   ██║      ██║   ██╔══╝  ██╔══██╗  ╚██╔╝
+ ██████╔╝██║  ██║   ██║      ██║   ███████╗██║  ██║   ██║
+ ╚═════╝ ╚═╝  ╚═╝   ╚═╝      ╚═╝   ╚══════╝╚═╝  ╚═╝   ╚═╝
+
+    All batteries added up by energy, so two half full batteries of
+    different sizes read what they hold together. Drivers that report
+    charge (µAh) instead of energy (µWh) are converted with the voltage.
+    A configured path (a power supply or a file in it) limits a sensor to one battery.
+*/
+
+/// Share of the newest power reading in the time estimates, at one sample per second.
+const SMOOTHING: f64 = 0.1;
+const HOUR: f64 = 3600.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// energy_now / energy_full in percent
+    Capacity,
+    /// 1 while any battery charges
+    Charging,
+    /// the power going in or out, in W
+    Power,
+    /// positive while charging, negative while discharging, in W
+    Rate,
+    TimeToEmpty,
+    TimeToFull,
+    /// energy_full / energy_full_design in percent
+    Health,
+}
+
+pub struct Battery {
+    kind: Kind,
+    batteries: Vec<PathBuf>,
+    /// smoothed power and the state it belongs to
+    smoothed: Option<(f64, State)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum State {
+    Charging,
+    Discharging,
+    Idle,
+}
+
+/// The sum of all batteries, energies in Wh and power in W.
+#[derive(Debug, PartialEq)]
+pub struct Totals {
+    pub energy_now: f64,
+    pub energy_full: f64,
+    pub energy_full_design: f64,
+    pub power: f64,
+    pub state: State,
+}
+
+impl Battery {
+    pub fn new(kind: Kind) -> Self {
+        Battery {
+            kind,
+            batteries: vec![],
+            smoothed: None,
+        }
+    }
+
+    fn time(&mut self, totals: &Totals, wanted: State) -> Reading {
+        let smoothed = match self.smoothed {
+            Some((power, state)) if state == totals.state => {
+                power + SMOOTHING * (totals.power - power)
+            }
+            // a new state starts over, the old rate says nothing about it
+            _ => totals.power,
+        };
+        self.smoothed = Some((smoothed, totals.state));
+        if totals.state != wanted || smoothed <= 0.0 {
+            // no estimate right now, but the sensor is still there
+            return Reading::Values(vec![]);
+        }
+        let energy = match wanted {
+            State::Charging => totals.energy_full - totals.energy_now,
+            _ => totals.energy_now,
+        };
+        Reading::Value(energy.max(0.0) / smoothed * HOUR)
+    }
+}
+
+impl Sensor for Battery {
+    fn name(&self) -> &str {
+        match self.kind {
+            Kind::Capacity => "battery.capacity",
+            Kind::Charging => "battery.charging",
+            Kind::Power => "battery.power",
+            Kind::Rate => "battery.rate",
+            Kind::TimeToEmpty => "battery.time_to_empty",
+            Kind::TimeToFull => "battery.time_to_full",
+            Kind::Health => "battery.health",
+        }
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        let batteries = match detected.configured(self.name()) {
+            Some(path) => vec![supply_dir(Path::new(path))],
+            None => (0..detected
+                .get("battery.count")
+                .map_or(0, |count| count.to_u64()))
+                .filter_map(|i| detected.get(&format!("battery[{}].path", i)))
+                .map(|path| PathBuf::from(path.to_string()))
+                .collect(),
+        };
+        if batteries != self.batteries {
+            self.batteries = batteries;
+            self.smoothed = None;
+        }
+        read_totals(&self.batteries).is_some()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let Some(totals) = read_totals(&self.batteries) else {
+            return Reading::Unavailable;
+        };
+        match self.kind {
+            Kind::Capacity if totals.energy_full > 0.0 => {
+                Reading::Value(100.0 * totals.energy_now / totals.energy_full)
+            }
+            Kind::Charging => Reading::Value((totals.state == State::Charging) as u8 as f64),
+            Kind::Power => Reading::Value(totals.power),
+            Kind::Rate => Reading::Value(match totals.state {
+                State::Discharging => -totals.power,
+                _ => totals.power,
+            }),
+            Kind::TimeToEmpty => self.time(&totals, State::Discharging),
+            Kind::TimeToFull => self.time(&totals, State::Charging),
+            Kind::Health if totals.energy_full_design > 0.0 => {
+                Reading::Value(100.0 * totals.energy_full / totals.energy_full_design)
+            }
+            _ => Reading::Values(vec![]),
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        match self.kind {
+            Kind::Capacity | Kind::Health => SensorUnit::Percent,
+            Kind::Charging => SensorUnit::Raw,
+            Kind::Power | Kind::Rate => SensorUnit::Watts,
+            Kind::TimeToEmpty | Kind::TimeToFull => SensorUnit::Seconds,
+        }
+    }
+
+    fn range(&self) -> (f64, f64) {
+        match self.kind {
+            Kind::Capacity | Kind::Health => (0.0, 100.0),
+            Kind::Charging => (0.0, 1.0),
+            Kind::Power => (0.0, 60.0),
+            Kind::Rate => (-60.0, 60.0),
+            Kind::TimeToEmpty | Kind::TimeToFull => (0.0, 8.0 * HOUR),
+        }
+    }
+
+    fn poll_interval(&self) -> Duration {
+        match self.kind {
+            Kind::Health => Duration::from_secs(60),
+            _ => Duration::from_secs(1),
+        }
+    }
+
+    fn source(&self) -> String {
+        let batteries = self
+            .batteries
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>();
+        batteries.join(", ")
+    }
+}
+
+/*
+ ████████╗ ██████╗ ████████╗ █████╗ ██╗     ███████╗
+ ╚══██╔══╝██╔═══██╗╚══██╔══╝██╔══██╗██║     ██╔════╝
+    ██║   ██║   ██║   ██║   ███████║██║     ███████╗
+    ██║   ██║   ██║   ██║   ██╔══██║██║     ╚════██║
+    ██║   ╚██████╔╝   ██║   ██║  ██║███████╗███████║
+    ╚═╝    ╚═════╝    ╚═╝   ╚═╝  ╚═╝╚══════╝╚══════╝
+*/
+
+/// `/sys/class/power_supply/BAT0/capacity` and `/sys/class/power_supply/BAT0` are the same battery.
+fn supply_dir(path: &Path) -> PathBuf {
+    match path.is_file() {
+        true => path.parent().unwrap_or(path).to_path_buf(),
+        false => path.to_path_buf(),
+    }
+}
+
+/// `None` if no battery could be read at all.
+pub fn read_totals(batteries: &[PathBuf]) -> Option<Totals> {
+    let mut totals = Totals {
+        energy_now: 0.0,
+        energy_full: 0.0,
+        energy_full_design: 0.0,
+        power: 0.0,
+        state: State::Idle,
+    };
+    let mut found = false;
+    for battery in batteries {
+        let read = |file: &str| {
+            let text = read_string_from_file_sync(battery.join(file).to_str()?).ok()?;
+            text.parse::<f64>().ok()
+        };
+        // µV, the design minimum is what the charge counters are based on
+        let volts = read("voltage_min_design").or_else(|| read("voltage_now"));
+        let volts = volts.map(|uv| uv / 1e6);
+        // µWh directly, or µAh times the voltage
+        let energy = |name: &str| {
+            read(&format!("energy_{}", name))
+                .or_else(|| Some(read(&format!("charge_{}", name))? * volts?))
+                .map(|uwh| uwh / 1e6)
+        };
+        let (Some(now), Some(full)) = (energy("now"), energy("full")) else {
+            continue;
+        };
+        found = true;
+        totals.energy_now += now;
+        totals.energy_full += full;
+        totals.energy_full_design += energy("full_design").unwrap_or(full);
+        let power = read("power_now")
+            .or_else(|| Some(read("current_now")? * read("voltage_now")? / 1e6))
+            .unwrap_or(0.0);
+        // some drivers report discharging as negative
+        totals.power += power.abs() / 1e6;
+        let status = battery.join("status");
+        let status = read_string_from_file_sync(status.to_str()?).unwrap_or_default();
+        // one charging battery is enough, a discharging one wins over idle ones
+        totals.state = match (status.as_str(), totals.state) {
+            ("Charging", _) | (_, State::Charging) => State::Charging,
+            ("Discharging", _) => State::Discharging,
+            (_, state) => state,
+        };
+    }
+    found.then_some(totals)
+}
@@ -0,0 +1,244 @@
+use super::{read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use crate::utils::global;
+use std::time::{Duration, Instant};
+
+/*
+  ██████╗██████╗ ██╗   ██╗
+ ██╔════╝██╔══██╗██║   ██║
+ ██║     ██████╔╝██║   ██║
+ ██║     ██╔═══╝ ██║   ██║
+ ╚██████╗██║     ╚██████╔╝
+  ╚═════╝╚═╝      ╚═════╝
+
+    Per core load from /proc/stat, one value per `cpuN` line. `cpu.usage`
+    and its split into user, system, iowait and steal below.
+*/
+
+const PROC_STAT: &str = "/proc/stat";
+
+pub struct CpuLoad {
+    path: String,
+    last_idle: Vec<u64>,
+    last_total: Vec<u64>,
+}
+
+impl CpuLoad {
+    pub fn new() -> Self {
+        CpuLoad {
+            path: String::new(),
+            last_idle: vec![],
+            last_total: vec![],
+        }
+    }
+}
+
+impl Sensor for CpuLoad {
+    fn name(&self) -> &str {
+        "cpu.load"
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.path = detected.file(PROC_STAT);
+        read_string_from_file_sync(&self.path).is_ok()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let Ok(contents) = read_string_from_file_sync(&self.path) else {
+            return Reading::Unavailable;
+        };
+        let mut loads = vec![];
+        // the first line is the sum of all cores
+        for line in contents.lines().skip(1) {
+            if !line.starts_with("cpu") {
+                break;
+            }
+            let cpu: Vec<u64> = line
+                .split_whitespace()
+                .skip(1)
+                .filter_map(|s| s.parse().ok())
+                .collect();
+            let total: u64 = cpu.iter().sum();
+            // idle + iowait
+            let idle = cpu.get(3).unwrap_or(&0) + cpu.get(4).unwrap_or(&0);
+            let i = loads.len();
+            if self.last_total.len() <= i {
+                self.last_total.push(0);
+                self.last_idle.push(0);
+            }
+            let relative_total = total.saturating_sub(self.last_total[i]);
+            let relative_idle = idle.saturating_sub(self.last_idle[i]);
+            let usage = if relative_total > 0 {
+                100.0 * relative_total.saturating_sub(relative_idle) as f64 / relative_total as f64
+            } else {
+                0.0
+            };
+            self.last_total[i] = total;
+            self.last_idle[i] = idle;
+            loads.push(usage);
+        }
+        self.last_total.truncate(loads.len());
+        self.last_idle.truncate(loads.len());
+        Reading::Values(loads)
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Percent
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 100.0)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(1000 / 30)
+    }
+
+    fn source(&self) -> String {
+        self.path.clone()
+    }
+}
+
+/*
+ ██╗   ██╗███████╗ █████╗  ██████╗ ███████╗
+ ██║   ██║██╔════╝██╔══██╗██╔════╝ ██╔════╝
+ ██║   ██║███████╗███████║██║  ███╗█████╗
+ ██║   ██║╚════██║██╔══██║██║   ██║██╔══╝
+ ╚██████╔╝███████║██║  ██║╚██████╔╝███████╗
+  ╚═════╝ ╚══════╝╚═╝  ╚═╝ ╚═════╝ ╚══════╝
+
+    All cores together from the `cpu` line: user nice system idle iowait
+    irq softirq steal guest guest_nice, in USER_HZ. Guest time is in user
+    and nice already, so only the first eight count.
+*/
+
+/// The usage sensors sample in the same tick, whichever comes first reads
+/// for all of them. Shorter than their poll interval, longer than a tick.
+const SHARED_READ: Duration = Duration::from_millis(100);
+
+global!(cpu_times, CpuTimes, CpuTimes::default());
+
+#[derive(Default)]
+pub struct CpuTimes {
+    path: String,
+    times: Option<[u64; 8]>,
+    read_at: Option<Instant>,
+}
+
+impl CpuTimes {
+    /// Reads the `cpu` line from `path` unless another sensor just did.
+    pub fn refresh(&mut self, path: &str, now: Instant) -> Option<[u64; 8]> {
+        let fresh = self
+            .read_at
+            .is_some_and(|at| now.duration_since(at) < SHARED_READ);
+        if !fresh || self.path != path {
+            self.path = path.to_string();
+            self.read_at = Some(now);
+            let contents = read_string_from_file_sync(path).ok();
+            self.times = contents.as_deref().and_then(parse_times);
+        }
+        self.times
+    }
+}
+
+pub fn parse_times(contents: &str) -> Option<[u64; 8]> {
+    let line = contents.lines().find_map(|x| x.strip_prefix("cpu "))?;
+    let mut times = [0u64; 8];
+    let fields = line.split_whitespace().filter_map(|x| x.parse().ok());
+    for (time, field) in times.iter_mut().zip(fields) {
+        *time = field;
+    }
+    Some(times)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// everything but idle and iowait
+    Usage,
+    /// user and nice
+    User,
+    /// system, irq and softirq
+    System,
+    Iowait,
+    /// taken by the hypervisor
+    Steal,
+}
+
+pub struct CpuUsage {
+    kind: Kind,
+    path: String,
+    last: [u64; 8],
+}
+
+impl CpuUsage {
+    pub fn new(kind: Kind) -> Self {
+        CpuUsage {
+            kind,
+            path: String::new(),
+            last: [0; 8],
+        }
+    }
+
+    /// Percent of the time since the last `cpu` line, since boot for the first one.
+    pub fn usage(&mut self, times: [u64; 8]) -> f64 {
+        let last = std::mem::replace(&mut self.last, times);
+        let delta = |i: usize| times[i].saturating_sub(last[i]);
+        let total = (0..8).map(delta).sum::<u64>();
+        if total == 0 {
+            return 0.0;
+        }
+        let time = match self.kind {
+            Kind::Usage => total - delta(3) - delta(4),
+            Kind::User => delta(0) + delta(1),
+            Kind::System => delta(2) + delta(5) + delta(6),
+            Kind::Iowait => delta(4),
+            Kind::Steal => delta(7),
+        };
+        100.0 * time as f64 / total as f64
+    }
+}
+
+impl Sensor for CpuUsage {
+    fn name(&self) -> &str {
+        match self.kind {
+            Kind::Usage => "cpu.usage",
+            Kind::User => "cpu.user",
+            Kind::System => "cpu.system",
+            Kind::Iowait => "cpu.iowait",
+            Kind::Steal => "cpu.steal",
+        }
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        let path = detected.file(PROC_STAT);
+        if path != self.path {
+            self.path = path;
+            self.last = [0; 8];
+        }
+        read_string_from_file_sync(&self.path).is_ok_and(|x| x.starts_with("cpu "))
+    }
+
+    fn sample(&mut self) -> Reading {
+        match cpu_times().refresh(&self.path, Instant::now()) {
+            Some(times) => Reading::Value(self.usage(times)),
+            None => Reading::Unavailable,
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Percent
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 100.0)
+    }
+
+    /// Slower than `cpu.load`, the split of a few ticks is mostly noise.
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    fn source(&self) -> String {
+        self.path.clone()
+    }
+}
@@ -0,0 +1,278 @@
+use super::{read_number_from_file_sync, read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/*
+  ██████╗██████╗ ██╗   ██╗███████╗██████╗ ███████╗ ██████╗
+ ██╔════╝██╔══██╗██║   ██║██╔════╝██╔══██╗██╔════╝██╔═══██╗
+ ██║     ██████╔╝██║   ██║█████╗  ██████╔╝█████╗  ██║   ██║
+ ██║     ██╔═══╝ ██║   ██║██╔══╝  ██╔══██╗██╔══╝  ██║▄▄ ██║
+ ╚██████╗██║     ╚██████╔╝██║     ██║  ██║███████╗╚██████╔╝
+  ╚═════╝╚═╝      ╚═════╝ ╚═╝     ╚═╝  ╚═╝╚══════╝ ╚══▀▀═╝
+
+    `cpu.freq` per core from cpufreq (kHz there, MHz here), the governor
+    and energy_performance_preference of the first core (they are set for
+    all of them together) and the thermal_throttle counts of intel cores.
+*/
+
+/// The cpufreq dirs (or throttle counters) detection found as `cpu[i].<key>`, by core.
+fn per_core(detected: &Detected, key: &str) -> Vec<PathBuf> {
+    let cores = detected.get("cpu.count").map_or(0, |count| count.to_u64());
+    (0..cores)
+        .filter_map(|i| detected.get(&format!("cpu[{}].{}", i, key)))
+        .map(|path| PathBuf::from(path.to_string()))
+        .collect()
+}
+
+fn read(path: PathBuf) -> Option<u64> {
+    read_number_from_file_sync(path.to_str()?).ok()
+}
+
+pub struct CpuFreq {
+    dirs: Vec<PathBuf>,
+    /// the fastest cpuinfo_max_freq, in MHz
+    max: f64,
+}
+
+impl CpuFreq {
+    pub fn new() -> Self {
+        CpuFreq {
+            dirs: vec![],
+            max: 0.0,
+        }
+    }
+}
+
+impl Sensor for CpuFreq {
+    fn name(&self) -> &str {
+        "cpu.freq"
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.dirs = per_core(detected, "cpufreq");
+        let max = self
+            .dirs
+            .iter()
+            .map(|dir| read(dir.join("cpuinfo_max_freq")));
+        self.max = max.flatten().max().unwrap_or(0) as f64 / 1000.0;
+        !self.dirs.is_empty()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let freqs = self
+            .dirs
+            .iter()
+            .map(|dir| read(dir.join("scaling_cur_freq")))
+            .collect::<Vec<_>>();
+        if freqs.iter().all(Option::is_none) {
+            return Reading::Unavailable;
+        }
+        // an offline core reads as 0 until it is back
+        let mhz = freqs.iter().map(|x| x.unwrap_or(0) as f64 / 1000.0);
+        Reading::Values(mhz.collect())
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Mhz
+    }
+
+    /// Up to the fastest boost clock, 5 GHz if cpufreq does not say.
+    fn range(&self) -> (f64, f64) {
+        match self.max > 0.0 {
+            true => (0.0, self.max),
+            false => (0.0, 5000.0),
+        }
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    fn source(&self) -> String {
+        match self.dirs.first() {
+            Some(dir) => format!("{} (+{})", dir.display(), self.dirs.len() - 1),
+            None => String::new(),
+        }
+    }
+}
+
+/*
+ ██████╗  ██████╗ ██╗     ██╗ ██████╗██╗   ██╗
+ ██╔══██╗██╔═══██╗██║     ██║██╔════╝╚██╗ ██╔╝
+ ██████╔╝██║   ██║██║     ██║██║      ╚████╔╝
+ ██╔═══╝ ██║   ██║██║     ██║██║       ╚██╔╝
+ ██║     ╚██████╔╝███████╗██║╚██████╗   ██║
+ ╚═╝      ╚═════╝ ╚══════╝╚═╝ ╚═════╝   ╚═╝
+
+    The value is the position in `GOVERNORS` or `PREFERENCES`, from fast to
+    frugal, so it can go in a shader. Unknown names have no value, the
+    text is always the name.
+*/
+
+pub const GOVERNORS: &[&str] = &[
+    "performance",
+    "schedutil",
+    "ondemand",
+    "conservative",
+    "userspace",
+    "powersave",
+];
+pub const PREFERENCES: &[&str] = &[
+    "performance",
+    "balance_performance",
+    "default",
+    "balance_power",
+    "power",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Policy {
+    Governor,
+    /// energy_performance_preference, intel_pstate and amd-pstate in active mode
+    Preference,
+}
+
+pub struct CpuPolicy {
+    policy: Policy,
+    path: String,
+    current: Option<String>,
+}
+
+impl CpuPolicy {
+    pub fn new(policy: Policy) -> Self {
+        CpuPolicy {
+            policy,
+            path: String::new(),
+            current: None,
+        }
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        match self.policy {
+            Policy::Governor => GOVERNORS,
+            Policy::Preference => PREFERENCES,
+        }
+    }
+}
+
+impl Sensor for CpuPolicy {
+    fn name(&self) -> &str {
+        match self.policy {
+            Policy::Governor => "cpu.governor",
+            Policy::Preference => "cpu.epp",
+        }
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        let file = match self.policy {
+            Policy::Governor => "scaling_governor",
+            Policy::Preference => "energy_performance_preference",
+        };
+        let Some(dir) = per_core(detected, "cpufreq").into_iter().next() else {
+            return false;
+        };
+        self.path = dir.join(file).to_string_lossy().to_string();
+        self.current = None;
+        read_string_from_file_sync(&self.path).is_ok()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let Ok(name) = read_string_from_file_sync(&self.path) else {
+            self.current = None;
+            return Reading::Unavailable;
+        };
+        let position = self.names().iter().position(|x| *x == name);
+        self.current = Some(name);
+        match position {
+            Some(i) => Reading::Value(i as f64),
+            None => Reading::Values(vec![]),
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Raw
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, (self.names().len() - 1) as f64)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn source(&self) -> String {
+        self.path.clone()
+    }
+
+    fn text(&self) -> Option<String> {
+        self.current.clone()
+    }
+}
+
+/*
+ ████████╗██╗  ██╗██████╗  ██████╗ ████████╗████████╗██╗     ███████╗
+ ╚══██╔══╝██║  ██║██╔══██╗██╔═══██╗╚══██╔══╝╚══██╔══╝██║     ██╔════╝
+    ██║   ███████║██████╔╝██║   ██║   ██║      ██║   ██║     █████╗
+    ██║   ██╔══██║██╔══██╗██║   ██║   ██║      ██║   ██║     ██╔══╝
+    ██║   ██║  ██║██║  ██║╚██████╔╝   ██║      ██║   ███████╗███████╗
+    ╚═╝   ╚═╝  ╚═╝╚═╝  ╚═╝ ╚═════╝    ╚═╝      ╚═╝   ╚══════╝╚══════╝
+
+    How often each core got too hot since boot. Threads of one core share
+    the counter. The graph goes up to the highest count so far.
+*/
+
+pub struct CpuThrottle {
+    paths: Vec<PathBuf>,
+    highest: f64,
+}
+
+impl CpuThrottle {
+    pub fn new() -> Self {
+        CpuThrottle {
+            paths: vec![],
+            highest: 0.0,
+        }
+    }
+}
+
+impl Sensor for CpuThrottle {
+    fn name(&self) -> &str {
+        "cpu.throttle"
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.paths = per_core(detected, "throttle");
+        !self.paths.is_empty()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let counts = self
+            .paths
+            .iter()
+            .map(|path| read(path.clone()).unwrap_or(0) as f64)
+            .collect::<Vec<_>>();
+        self.highest = counts.iter().copied().fold(self.highest, f64::max);
+        Reading::Values(counts)
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Raw
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, self.highest.max(1.0))
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn source(&self) -> String {
+        match self.paths.first() {
+            Some(path) => format!("{} (+{})", path.display(), self.paths.len() - 1),
+            None => String::new(),
+        }
+    }
+}
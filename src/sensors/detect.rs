@@ -1,5 +1,7 @@
+use super::{pci, rooted};
 use crate::utils::early_continue;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 /*
  ███████╗██╗   ██╗███████╗███████╗███████╗
@@ -10,47 +12,70 @@ use std::collections::HashMap;
  ╚══════╝   ╚═╝   ╚══════╝╚═╝     ╚══════╝
 */
 
-pub async fn detect_sensors() -> HashMap<String, Value> {
+/// Everything below `root`, which is `/` unless `--root` or $SHADERBAR_ROOT say otherwise.
+pub async fn detect_sensors(root: &Path) -> HashMap<String, Value> {
     let mut detected: HashMap<String, Value> = HashMap::new();
     detected.insert("cpu.count".to_string(), Value::U64(0));
     detected.insert("gpu.count".to_string(), Value::U64(0));
-    detected.insert(
-        "cpu.count".to_string(),
-        Value::U64(command_line("nproc").await.parse().unwrap()),
-    );
-    let labels = labels().await;
-    for (k, _) in match_regex(r"coretemp|k10temp|cpu|CPU", labels.clone()) {
+    let cpu_count = cpu_count(root)
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get() as u64));
+    detected.insert("cpu.count".to_string(), Value::U64(cpu_count));
+    let labels = labels(root).await;
+    // the first match in path order, usually temp1 (the package temperature)
+    if let Some((k, _)) = match_regex(r"coretemp|k10temp|cpu|CPU", labels.clone()).first_key_value()
+    {
         let temp_path = format!("{}_input", k.replace("_label", ""));
         let fan_path = temp_path.replace("temp", "fan");
         add(&mut detected, "cpu.temp", &temp_path);
         add(&mut detected, "cpu.fan", &fan_path);
     }
-    for (k, _) in match_regex(r"amdgpu|nouveau|nvidia|intel|GPU", labels) {
+    if let Some((k, _)) = match_regex(r"amdgpu|nouveau|nvidia|intel|GPU", labels).first_key_value()
+    {
         let temp_path = format!("{}_input", k.replace("_label", ""));
         let fan_path = temp_path.replace("temp", "fan");
         add(&mut detected, "gpu.temp", &temp_path);
         add(&mut detected, "gpu.fan", &fan_path);
     }
-    detected = detect_amdgpu(detected).await;
-    detected = detect_network(detected).await;
-    detected = detect_battery(detected).await;
+    detected = detect_amdgpu(root, detected).await;
+    detected = detect_drm(root, detected).await;
+    detected = detect_battery(root, detected).await;
+    detected = detect_ac(root, detected).await;
+    detected = detect_disks(root, detected).await;
+    detected = detect_zram(root, detected).await;
+    detected = detect_session(root, detected).await;
+    detected = detect_cpufreq(root, detected).await;
     detected
 }
 
-pub async fn labels() -> HashMap<String, Value> {
-    let mut map: HashMap<String, Value> = HashMap::new();
-    let keys = glob::glob("/sys/class/hwmon/hwmon*/*_label")
-        .unwrap()
-        .filter_map(Result::ok)
-        .collect::<Vec<_>>();
-    for key in keys {
+/// The `cpuN` lines of /proc/stat.
+fn cpu_count(root: &Path) -> Option<u64> {
+    let stat = std::fs::read_to_string(rooted(root, "/proc/stat")).ok()?;
+    let count = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu") && !line.starts_with("cpu "))
+        .count() as u64;
+    (count > 0).then_some(count)
+}
+
+/// Label files and their contents, prefixed with the hwmon `name`
+/// (`coretemp: Package id 0`) since most labels do not say what they belong to.
+pub async fn labels(root: &Path) -> BTreeMap<String, Value> {
+    let mut map: BTreeMap<String, Value> = BTreeMap::new();
+    for key in glob_sorted(root, "/sys/class/hwmon/hwmon*/*_label") {
+        let name = key.with_file_name("name");
         let key = key.to_str().unwrap().to_string();
+        // some drivers expose labels that can not be read
         let value = tokio::fs::read_to_string(&key)
             .await
-            .unwrap()
+            .unwrap_or_default()
             .trim()
             .to_string();
-        map.insert(key, Value::String(value));
+        let name = tokio::fs::read_to_string(&name)
+            .await
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        map.insert(key, Value::String(format!("{}: {}", name, value)));
     }
     map
 }
@@ -64,57 +89,50 @@ pub async fn labels() -> HashMap<String, Value> {
  ╚═╝  ╚═╝╚═╝     ╚═╝╚═════╝
 */
 
-async fn detect_amdgpu(mut detected: HashMap<String, Value>) -> HashMap<String, Value> {
+async fn detect_amdgpu(
+    root: &Path,
+    mut detected: HashMap<String, Value>,
+) -> HashMap<String, Value> {
     let gpu_count_key = &"gpu.count".to_string();
     let mut gpus = detected.get(gpu_count_key).unwrap().to_u64();
-    for line in command_lines("find /sys/devices/pci* -name gpu_busy_percent").await {
-        let path = line.trim().to_string();
+    for path in glob_sorted(root, "/sys/class/drm/card[0-9]*/device/gpu_busy_percent") {
+        let device_dir = path.parent().unwrap();
         let key = format!("gpu[{}].usage", gpus);
-        let dirname = dirname(path.clone());
-        detected.insert(key, Value::String(path.clone()));
-        let udevadm_call = format!(
-            "udevadm info -q property -p {} --property ID_MODEL_FROM_DATABASE",
-            dirname
-        );
-        let model = command_line(&udevadm_call).await.split_off(23);
+        add(&mut detected, &key, path.to_str().unwrap());
+        let model = pci::model(root, device_dir).unwrap_or("unknown".to_string());
         let key = format!("gpu[{}].model", gpus);
-        detected.insert(key, Value::String(model));
+        add(&mut detected, &key, &model);
         gpus += 1;
     }
     detected.insert("gpu.count".to_string(), Value::U64(gpus));
     detected
 }
 
-/*
- ███╗   ██╗███████╗████████╗██╗    ██╗ ██████╗ ██████╗ ██╗  ██╗
- ████╗  ██║██╔════╝╚══██╔══╝██║    ██║██╔═══██╗██╔══██╗██║ ██╔╝
- ██╔██╗ ██║█████╗     ██║   ██║ █╗ ██║██║   ██║██████╔╝█████╔╝
- ██║╚██╗██║██╔══╝     ██║   ██║███╗██║██║   ██║██╔══██╗██╔═██╗
- ██║ ╚████║███████╗   ██║   ╚███╔███╔╝╚██████╔╝██║  ██║██║  ██╗
- ╚═╝  ╚═══╝╚══════╝   ╚═╝    ╚══╝╚══╝  ╚═════╝ ╚═╝  ╚═╝╚═╝  ╚═╝
-*/
-
-async fn detect_network(mut detected: HashMap<String, Value>) -> HashMap<String, Value> {
-    for line in command_lines("nmcli device").await {
-        let words = line.split_whitespace().collect::<Vec<&str>>();
-        early_continue!(words.len() < 3 || words[2] != "connected");
-        match words[1] {
-            "wifi" => {
-                add(&mut detected, "wifi.interface", words[0]);
-                add(&mut detected, "wifi.connection", words[3]);
-            }
-            "ethernet" => {
-                add(&mut detected, "ethernet.interface", words[0]);
-                add(&mut detected, "ethernet.connection", words[3]);
-            }
-            "gsm" => {
-                add(&mut detected, "gsm.interface", words[0]);
-                add(&mut detected, "gsm.connection", words[3]);
-            }
-            _ => (),
+/// Every DRM card with its driver and PCI address from the uevent, what
+/// fdinfo calls `drm-driver` and `drm-pdev`. `card0-eDP-1` and friends are
+/// connectors of a card, not cards.
+async fn detect_drm(root: &Path, mut map: HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut cards = 0;
+    for dir in glob_sorted(root, "/sys/class/drm/card[0-9]*") {
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
+        early_continue!(name.trim_start_matches("card").parse::<u64>().is_err());
+        let uevent = std::fs::read_to_string(dir.join("device/uevent")).unwrap_or_default();
+        let field = |key: &str| {
+            uevent
+                .lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+        };
+        let Some(driver) = field("DRIVER") else {
+            continue;
+        };
+        add(&mut map, &format!("drm[{}].driver", cards), driver);
+        if let Some(pdev) = field("PCI_SLOT_NAME") {
+            add(&mut map, &format!("drm[{}].pdev", cards), pdev);
         }
+        cards += 1;
     }
-    detected
+    map.insert("drm.count".to_string(), Value::U64(cards));
+    map
 }
 
 fn add(detected: &mut HashMap<String, Value>, key: &str, value: &str) {
@@ -130,16 +148,145 @@ fn add(detected: &mut HashMap<String, Value>, key: &str, value: &str) {
  ╚═════╝ ╚═╝  ╚═╝   ╚═╝      ╚═╝   ╚══════╝╚═╝  ╚═╝   ╚═╝
 */
 
-async fn detect_battery(mut map: HashMap<String, Value>) -> HashMap<String, Value> {
-    for line in command_lines("ls /sys/class/power_supply/*/capacity").await {
-        let path = line.trim().to_string();
-        add(&mut map, "battery.capacity", &path);
-        add(
-            &mut map,
-            "battery.status",
-            &path.replace("/capacity", "/status"),
+/// Every system battery, mice and headsets report `scope` Device.
+async fn detect_battery(root: &Path, mut map: HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut batteries = 0;
+    for dir in glob_sorted(root, "/sys/class/power_supply/*") {
+        let read = |file: &str| {
+            std::fs::read_to_string(dir.join(file))
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        early_continue!(read("type") != "Battery" || read("scope") == "Device");
+        let key = format!("battery[{}].path", batteries);
+        add(&mut map, &key, dir.to_str().unwrap());
+        batteries += 1;
+    }
+    map.insert("battery.count".to_string(), Value::U64(batteries));
+    map
+}
+
+/// AC adapters, the power supplies of type Mains.
+async fn detect_ac(root: &Path, mut map: HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut adapters = 0;
+    for dir in glob_sorted(root, "/sys/class/power_supply/*") {
+        let kind = std::fs::read_to_string(dir.join("type")).unwrap_or_default();
+        early_continue!(kind.trim() != "Mains");
+        let key = format!("ac[{}].path", adapters);
+        add(&mut map, &key, dir.to_str().unwrap());
+        adapters += 1;
+    }
+    map.insert("ac.count".to_string(), Value::U64(adapters));
+    map
+}
+
+/*
+ ██████╗ ██╗███████╗██╗  ██╗███████╗
+ ██╔══██╗██║██╔════╝██║ ██╔╝██╔════╝
+ ██║  ██║██║███████╗█████╔╝ ███████╗
+ ██║  ██║██║╚════██║██╔═██╗ ╚════██║
+ ██████╔╝██║███████║██║  ██╗███████║
+ ╚═════╝ ╚═╝╚══════╝╚═╝  ╚═╝╚══════╝
+*/
+
+/// Whole disks from /sys/block (partitions are not listed there). Loop, zram,
+/// dm and md devices have no `device` behind them, empty card readers have size 0.
+async fn detect_disks(root: &Path, mut map: HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut disks = 0;
+    for dir in glob_sorted(root, "/sys/block/*") {
+        let size = std::fs::read_to_string(dir.join("size")).unwrap_or_default();
+        early_continue!(!dir.join("device").exists() || size.trim() == "0");
+        let key = format!("disk[{}].name", disks);
+        add(&mut map, &key, &dir.file_name().unwrap().to_string_lossy());
+        disks += 1;
+    }
+    map.insert("disk.count".to_string(), Value::U64(disks));
+    map
+}
+
+/*
+  ██████╗██████╗ ██╗   ██╗███████╗██████╗ ███████╗ ██████╗
+ ██╔════╝██╔══██╗██║   ██║██╔════╝██╔══██╗██╔════╝██╔═══██╗
+ ██║     ██████╔╝██║   ██║█████╗  ██████╔╝█████╗  ██║   ██║
+ ██║     ██╔═══╝ ██║   ██║██╔══╝  ██╔══██╗██╔══╝  ██║▄▄ ██║
+ ╚██████╗██║     ╚██████╔╝██║     ██║  ██║███████╗╚██████╔╝
+  ╚═════╝╚═╝      ╚═════╝ ╚═╝     ╚═╝  ╚═╝╚══════╝ ╚══▀▀═╝
+*/
+
+/// The cpufreq and thermal_throttle dirs of each core, keyed by the core number
+/// (glob puts cpu10 before cpu2). VMs usually have neither.
+async fn detect_cpufreq(root: &Path, mut map: HashMap<String, Value>) -> HashMap<String, Value> {
+    for dir in glob_sorted(root, "/sys/devices/system/cpu/cpu[0-9]*") {
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
+        let Ok(core) = name.trim_start_matches("cpu").parse::<u64>() else {
+            continue;
+        };
+        let cpufreq = dir.join("cpufreq");
+        if cpufreq.join("scaling_cur_freq").exists() {
+            let key = format!("cpu[{}].cpufreq", core);
+            add(&mut map, &key, cpufreq.to_str().unwrap());
+        }
+        // intel only
+        let throttle = dir.join("thermal_throttle/core_throttle_count");
+        if throttle.exists() {
+            let key = format!("cpu[{}].throttle", core);
+            add(&mut map, &key, throttle.to_str().unwrap());
+        }
+    }
+    map
+}
+
+/*
+ ███████╗██████╗  █████╗ ███╗   ███╗
+ ╚══███╔╝██╔══██╗██╔══██╗████╗ ████║
+   ███╔╝ ██████╔╝███████║██╔████╔██║
+  ███╔╝  ██╔══██╗██╔══██║██║╚██╔╝██║
+ ███████╗██║  ██║██║  ██║██║ ╚═╝ ██║
+ ╚══════╝╚═╝  ╚═╝╚═╝  ╚═╝╚═╝     ╚═╝
+*/
+
+/// zram devices that are set up, an unused one has size 0 and no mm_stat to speak of.
+async fn detect_zram(root: &Path, mut map: HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut devices = 0;
+    for dir in glob_sorted(root, "/sys/block/zram*") {
+        let size = std::fs::read_to_string(dir.join("size")).unwrap_or_default();
+        early_continue!(!dir.join("mm_stat").exists() || size.trim() == "0");
+        let key = format!("zram[{}].path", devices);
+        add(&mut map, &key, dir.to_str().unwrap());
+        devices += 1;
+    }
+    map.insert("zram.count".to_string(), Value::U64(devices));
+    map
+}
+
+/*
+ ███████╗███████╗███████╗███████╗██╗ ██████╗ ███╗   ██╗
+ ██╔════╝██╔════╝██╔════╝██╔════╝██║██╔═══██╗████╗  ██║
+ ███████╗█████╗  ███████╗███████╗██║██║   ██║██╔██╗ ██║
+ ╚════██║██╔══╝  ╚════██║╚════██║██║██║   ██║██║╚██╗██║
+ ███████║███████╗███████║███████║██║╚██████╔╝██║ ╚████║
+ ╚══════╝╚══════╝╚══════╝╚══════╝╚═╝ ╚═════╝ ╚═╝  ╚═══╝
+*/
+
+/// The cgroup systemd puts the user's session in. Below another root the uid
+/// means nothing, so a single user slice is taken whoever it belongs to.
+async fn detect_session(root: &Path, mut map: HashMap<String, Value>) -> HashMap<String, Value> {
+    let uid = unsafe { libc::getuid() };
+    let own = format!(
+        "/sys/fs/cgroup/user.slice/user-{}.slice/memory.current",
+        uid
+    );
+    let mut found = glob_sorted(root, &own);
+    if found.is_empty() {
+        found = glob_sorted(
+            root,
+            "/sys/fs/cgroup/user.slice/user-*.slice/memory.current",
         );
-        break;
+        found.truncate((found.len() == 1) as usize);
+    }
+    if let Some(path) = found.first() {
+        add(&mut map, "memory.session.path", path.to_str().unwrap());
     }
     map
 }
@@ -153,66 +300,20 @@ async fn detect_battery(mut map: HashMap<String, Value>) -> HashMap<String, Valu
  ╚═╝  ╚═╝╚══════╝╚══════╝╚═╝     ╚══════╝╚═╝  ╚═╝╚══════╝
 */
 
-#[allow(dead_code)]
-async fn fs_exists(path: &str) -> bool {
-    tokio::fs::metadata(path).await.is_err()
-}
-
-fn match_regex(regex: &str, labels: HashMap<String, Value>) -> HashMap<String, Value> {
+fn match_regex(regex: &str, labels: BTreeMap<String, Value>) -> BTreeMap<String, Value> {
     let regex = regex::Regex::new(regex).unwrap();
     labels
         .into_iter()
         .filter(|(_, v)| regex.is_match(v.to_string().as_str()))
-        .collect::<HashMap<String, Value>>()
-}
-
-async fn command_line(command: &str) -> String {
-    String::from_utf8(
-        tokio::process::Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()
-            .await
-            .unwrap()
-            .stdout,
-    )
-    .unwrap()
-    .trim()
-    .to_string()
-}
-
-async fn command_lines(command: &str) -> Vec<String> {
-    String::from_utf8(
-        tokio::process::Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()
-            .await
-            .unwrap()
-            .stdout,
-    )
-    .unwrap()
-    .lines()
-    .map(|x| x.to_string())
-    .collect::<Vec<String>>()
+        .collect::<BTreeMap<String, Value>>()
 }
 
-fn dirname(path: String) -> String {
-    std::path::Path::new(&path)
-        .parent()
-        .unwrap()
-        .to_str()
+/// `pattern` below the root, glob sorts by path.
+fn glob_sorted(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    glob::glob(&rooted(root, pattern))
         .unwrap()
-        .to_string()
-}
-
-#[allow(dead_code)]
-pub async fn read_string_from_file(path: &str) -> String {
-    tokio::fs::read_to_string(path)
-        .await
-        .expect(format!("Failed to read file: {}", path).as_str())
-        .trim()
-        .to_string()
+        .filter_map(Result::ok)
+        .collect()
 }
 
 /*
@@ -235,12 +336,6 @@ impl Value {
             Value::String(x) => x.parse::<u64>().ok().unwrap(),
         }
     }
-    pub fn to_string(&self) -> String {
-        match self {
-            Value::U64(x) => x.to_string(),
-            Value::String(x) => x.to_string(),
-        }
-    }
 }
 
 impl std::fmt::Display for Value {
@@ -0,0 +1,252 @@
+use super::network::{counter_delta, Peak};
+use super::{read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::{config, DiskSettings, NetworkScale, SensorUnit};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/*
+ ██████╗ ██╗███████╗██╗  ██╗
+ ██╔══██╗██║██╔════╝██║ ██╔╝
+ ██║  ██║██║███████╗█████╔╝
+ ██║  ██║██║╚════██║██╔═██╗
+ ██████╔╝██║███████║██║  ██╗
+ ╚═════╝ ╚═╝╚══════╝╚═╝  ╚═╝
+
+    `disk.usage` is the used space of each $config.disk.mounts entry, like
+    `df` counts it. `disk.read`, `disk.write` and `disk.busy` come from
+    /proc/diskstats for each detected (or configured) disk.
+*/
+
+const PROC_DISKSTATS: &str = "/proc/diskstats";
+/// diskstats counts 512 byte sectors, whatever the disk uses
+const SECTOR: f64 = 512.0;
+
+pub struct DiskUsage {
+    /// the mount point and where it is below the root
+    mounts: Vec<(String, String)>,
+}
+
+impl DiskUsage {
+    pub fn new() -> Self {
+        DiskUsage { mounts: vec![] }
+    }
+}
+
+/// Percent used, reserved blocks count as neither used nor free.
+fn usage(path: &str) -> Option<f64> {
+    let path = CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let used = stat.f_blocks.saturating_sub(stat.f_bfree) as f64;
+    let total = used + stat.f_bavail as f64;
+    (total > 0.0).then(|| 100.0 * used / total)
+}
+
+impl Sensor for DiskUsage {
+    fn name(&self) -> &str {
+        "disk.usage"
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.mounts = detected
+            .disk()
+            .mounts
+            .iter()
+            .map(|mount| (mount.clone(), detected.file(mount)))
+            .filter(|(_, path)| Path::new(path).is_dir())
+            .collect();
+        !self.mounts.is_empty()
+    }
+
+    fn sample(&mut self) -> Reading {
+        // a mount that fails to stat is left out until it works again
+        let usages = self
+            .mounts
+            .iter()
+            .filter_map(|(_, path)| usage(path))
+            .collect::<Vec<_>>();
+        match usages.is_empty() {
+            true => Reading::Unavailable,
+            false => Reading::Values(usages),
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Percent
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 100.0)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn source(&self) -> String {
+        let mounts = self
+            .mounts
+            .iter()
+            .map(|(mount, _)| mount.as_str())
+            .collect::<Vec<_>>();
+        mounts.join(", ")
+    }
+}
+
+/*
+ ██████╗ ██╗███████╗██╗  ██╗███████╗████████╗ █████╗ ████████╗███████╗
+ ██╔══██╗██║██╔════╝██║ ██╔╝██╔════╝╚══██╔══╝██╔══██╗╚══██╔══╝██╔════╝
+ ██║  ██║██║███████╗█████╔╝ ███████╗   ██║   ███████║   ██║   ███████╗
+ ██║  ██║██║╚════██║██╔═██╗ ╚════██║   ██║   ██╔══██║   ██║   ╚════██║
+ ██████╔╝██║███████║██║  ██╗███████║   ██║   ██║  ██║   ██║   ███████║
+ ╚═════╝ ╚═╝╚══════╝╚═╝  ╚═╝╚══════╝   ╚═╝   ╚═╝  ╚═╝   ╚═╝   ╚══════╝
+
+    major minor name, then reads, reads merged, sectors read, ms reading,
+    writes, writes merged, sectors written, ms writing, in flight and
+    ms doing I/O. Busy is that last one against the time that passed.
+*/
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Read,
+    Write,
+    Busy,
+}
+
+pub struct DiskIo {
+    kind: Kind,
+    path: String,
+    devices: Vec<String>,
+    /// the last counter of each device and when it was read
+    counters: HashMap<String, (u64, Instant)>,
+    peak: Peak,
+    full_scale: f64,
+}
+
+impl DiskIo {
+    pub fn new(kind: Kind) -> Self {
+        DiskIo {
+            kind,
+            path: String::new(),
+            devices: vec![],
+            counters: HashMap::new(),
+            peak: Peak::new(),
+            full_scale: DiskSettings::default().min_peak,
+        }
+    }
+
+    /// Bytes/s (busy: percent) for each device, 0 for the first reading and after a reset.
+    pub fn rates(&mut self, contents: &str, now: Instant, settings: &DiskSettings) -> Vec<f64> {
+        // counted after the name
+        let column = match self.kind {
+            Kind::Read => 2,
+            Kind::Write => 6,
+            Kind::Busy => 9,
+        };
+        let mut rates = vec![0.0; self.devices.len()];
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace().skip(2);
+            let Some(name) = fields.next() else {
+                continue;
+            };
+            let Some(i) = self.devices.iter().position(|x| x == name) else {
+                continue;
+            };
+            let Some(counter) = fields.nth(column).and_then(|x| x.parse::<u64>().ok()) else {
+                continue;
+            };
+            let Some((last, at)) = self.counters.insert(name.to_string(), (counter, now)) else {
+                continue;
+            };
+            let seconds = now.duration_since(at).as_secs_f64();
+            let (Some(delta), true) = (counter_delta(last, counter), seconds > 0.0) else {
+                continue;
+            };
+            rates[i] = match self.kind {
+                Kind::Read | Kind::Write => delta as f64 * SECTOR / seconds,
+                Kind::Busy => (delta as f64 / 10.0 / seconds).min(100.0),
+            };
+        }
+        if self.kind != Kind::Busy {
+            let busiest = rates.iter().copied().fold(0.0, f64::max);
+            let peak = self
+                .peak
+                .update(busiest, now, NetworkScale::Decay, settings.peak_seconds);
+            self.full_scale = peak.max(settings.min_peak);
+        }
+        rates
+    }
+}
+
+impl Sensor for DiskIo {
+    fn name(&self) -> &str {
+        match self.kind {
+            Kind::Read => "disk.read",
+            Kind::Write => "disk.write",
+            Kind::Busy => "disk.busy",
+        }
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        let path = detected.file(PROC_DISKSTATS);
+        if path != self.path {
+            self.path = path;
+            self.counters.clear();
+        }
+        self.devices = match detected.disk().devices.is_empty() {
+            false => detected.disk().devices.clone(),
+            true => (0..detected.get("disk.count").map_or(0, |count| count.to_u64()))
+                .filter_map(|i| detected.get(&format!("disk[{}].name", i)))
+                .map(|name| name.to_string())
+                .collect(),
+        };
+        let Ok(contents) = read_string_from_file_sync(&self.path) else {
+            return false;
+        };
+        // configured devices can be gone, detected ones can be missing from diskstats
+        let listed = |device: &String| {
+            contents
+                .lines()
+                .any(|line| line.split_whitespace().nth(2) == Some(device.as_str()))
+        };
+        self.devices.retain(listed);
+        let devices = &self.devices;
+        self.counters.retain(|device, _| devices.contains(device));
+        !self.devices.is_empty()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let Ok(contents) = read_string_from_file_sync(&self.path) else {
+            return Reading::Unavailable;
+        };
+        let settings = &config().settings.disk;
+        Reading::Values(self.rates(&contents, Instant::now(), settings))
+    }
+
+    fn unit(&self) -> SensorUnit {
+        match self.kind {
+            Kind::Read | Kind::Write => SensorUnit::Bytes,
+            Kind::Busy => SensorUnit::Percent,
+        }
+    }
+
+    /// Read and write move with the traffic like `network.rx`.
+    fn range(&self) -> (f64, f64) {
+        match self.kind {
+            Kind::Read | Kind::Write => (0.0, self.full_scale),
+            Kind::Busy => (0.0, 100.0),
+        }
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(250)
+    }
+
+    fn source(&self) -> String {
+        format!("{} ({})", self.path, self.devices.join(", "))
+    }
+}
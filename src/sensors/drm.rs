@@ -0,0 +1,440 @@
+use super::processes::parse_stat;
+use super::{Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use crate::utils::global;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/*
+ ██████╗ ██████╗ ███╗   ███╗
+ ██╔══██╗██╔══██╗████╗ ████║
+ ██║  ██║██████╔╝██╔████╔██║
+ ██║  ██║██╔══██╗██║╚██╔╝██║
+ ██████╔╝██║  ██║██║ ╚═╝ ██║
+ ╚═════╝ ╚═╝  ╚═╝╚═╝     ╚═╝
+
+    GPU engine usage from DRM fdinfo, the way `intel_gpu_top` and `nvtop`
+    get it. Every open /dev/dri file has a /proc/[pid]/fdinfo entry with
+    the busy time of each engine for that client. Added up over all
+    clients of a GPU, against the time between two walks, that is the
+    usage of the engine. Works on i915, xe, amdgpu, msm and nouveau, the
+    engines are named by the driver and sorted into render, video and
+    compute here. `gpu.busy` is the busiest engine of any kind.
+
+    drm-driver:     i915
+    drm-pdev:       0000:00:02.0
+    drm-client-id:  42
+    drm-engine-render:      25662044495 ns
+    drm-engine-capacity-video:      2
+
+    xe counts GPU cycles instead, against the cycles that went by:
+
+    drm-cycles-rcs: 28257900
+    drm-total-cycles-rcs:   7655183225
+*/
+
+/// Drivers with engine stats in fdinfo.
+const DRIVERS: &[&str] = &["i915", "xe", "amdgpu", "msm", "nouveau"];
+/// The per engine keys, `drm-engine-capacity-` has to come before `drm-engine-`.
+const COUNTERS: &[&str] = &[
+    "drm-engine-capacity-",
+    "drm-engine-",
+    "drm-total-cycles-",
+    "drm-cycles-",
+];
+/// The sensors share one walk, whichever samples first does it.
+const WALK_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Engine {
+    pub name: String,
+    /// ns busy, GPU cycles on xe
+    pub busy: u64,
+    /// xe only, the cycles that went by
+    pub total: Option<u64>,
+    /// engines of this kind, i915 adds up both video engines in one counter
+    pub capacity: u64,
+}
+
+/// One open DRM file. The client id is unique per device, a dup'd or
+/// inherited file shows up with the same id in every process that has it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Client {
+    pub driver: String,
+    /// the PCI address, platform GPUs like msm have none
+    pub pdev: Option<String>,
+    pub id: u64,
+    pub engines: Vec<Engine>,
+}
+
+impl Client {
+    /// What `devices` are matched against: the PCI address, or the driver.
+    fn device(&self) -> &str {
+        self.pdev.as_deref().unwrap_or(&self.driver)
+    }
+}
+
+/// `None` for anything that is not a DRM file.
+pub fn parse_fdinfo(contents: &str) -> Option<Client> {
+    let (mut driver, mut pdev, mut id) = (None, None, None);
+    let mut engines: Vec<Engine> = vec![];
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        // `123 ns`, cycles and capacities have no unit
+        let number = value
+            .split_whitespace()
+            .next()
+            .and_then(|x| x.parse::<u64>().ok());
+        match key {
+            "drm-driver" => driver = Some(value.to_string()),
+            "drm-pdev" => pdev = Some(value.to_string()),
+            "drm-client-id" => id = number,
+            _ => {}
+        }
+        let Some((prefix, name)) = COUNTERS
+            .iter()
+            .find_map(|prefix| Some((*prefix, key.strip_prefix(prefix)?)))
+        else {
+            continue;
+        };
+        let Some(number) = number else {
+            continue;
+        };
+        let i = match engines.iter().position(|x| x.name == name) {
+            Some(i) => i,
+            None => {
+                engines.push(Engine {
+                    name: name.to_string(),
+                    busy: 0,
+                    total: None,
+                    capacity: 1,
+                });
+                engines.len() - 1
+            }
+        };
+        match prefix {
+            "drm-engine-capacity-" => engines[i].capacity = number.max(1),
+            "drm-total-cycles-" => engines[i].total = Some(number),
+            _ => engines[i].busy = number,
+        }
+    }
+    Some(Client {
+        driver: driver?,
+        pdev,
+        id: id?,
+        engines,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Class {
+    /// any engine
+    Busy,
+    Render,
+    Video,
+    Compute,
+}
+
+impl Class {
+    /// Copy engines and whatever a driver adds later count for `Busy` only.
+    pub fn of(engine: &str) -> Option<Class> {
+        // `video-enhance` on i915, `enc_1` on amdgpu
+        match engine.split(['-', '_']).next()? {
+            "render" | "rcs" | "gfx" | "gpu" | "gr" => Some(Class::Render),
+            "video" | "vcs" | "vecs" | "dec" | "enc" | "jpeg" | "vcn" | "vpe" | "nvdec"
+            | "nvenc" => Some(Class::Video),
+            "compute" | "ccs" => Some(Class::Compute),
+            _ => None,
+        }
+    }
+}
+
+/*
+ ███████╗██████╗ ██╗███╗   ██╗███████╗ ██████╗
+ ██╔════╝██╔══██╗██║████╗  ██║██╔════╝██╔═══██╗
+ █████╗  ██║  ██║██║██╔██╗ ██║█████╗  ██║   ██║
+ ██╔══╝  ██║  ██║██║██║╚██╗██║██╔══╝  ██║   ██║
+ ██║     ██████╔╝██║██║ ╚████║██║     ╚██████╔╝
+ ╚═╝     ╚═════╝ ╚═╝╚═╝  ╚═══╝╚═╝      ╚═════╝
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessClient {
+    pub pid: u32,
+    pub comm: String,
+    pub client: Client,
+}
+
+/// Every DRM client below `proc_dir`, each once: a client shared by several
+/// processes goes to the lowest pid, usually the one that opened it.
+pub fn read_clients(proc_dir: &Path) -> Vec<ProcessClient> {
+    let Ok(dirs) = std::fs::read_dir(proc_dir) else {
+        return vec![];
+    };
+    let mut pids = dirs
+        .filter_map(Result::ok)
+        .filter_map(|dir| dir.file_name().to_str()?.parse::<u32>().ok())
+        .collect::<Vec<_>>();
+    pids.sort_unstable();
+    let mut seen = HashSet::new();
+    let mut clients = vec![];
+    for pid in pids {
+        let dir = proc_dir.join(pid.to_string());
+        // other users' processes can not be read, kernel threads have no files
+        let Ok(fds) = std::fs::read_dir(dir.join("fdinfo")) else {
+            continue;
+        };
+        let mut comm = None;
+        for fd in fds.filter_map(Result::ok) {
+            // most files are no DRM files, the link says so without reading fdinfo
+            let link = std::fs::read_link(dir.join("fd").join(fd.file_name()));
+            if link.is_ok_and(|x| !x.starts_with("/dev/dri")) {
+                continue;
+            }
+            let Ok(contents) = std::fs::read_to_string(fd.path()) else {
+                continue;
+            };
+            let Some(client) = parse_fdinfo(&contents) else {
+                continue;
+            };
+            if !seen.insert((client.device().to_string(), client.id)) {
+                continue;
+            }
+            let comm = comm.get_or_insert_with(|| {
+                let stat = std::fs::read_to_string(dir.join("stat")).unwrap_or_default();
+                parse_stat(&stat).map_or(String::new(), |stat| stat.comm.to_string())
+            });
+            clients.push(ProcessClient {
+                pid,
+                comm: comm.clone(),
+                client,
+            });
+        }
+    }
+    clients
+}
+
+/*
+ ███████╗███╗   ██╗ ██████╗ ██╗███╗   ██╗███████╗███████╗
+ ██╔════╝████╗  ██║██╔════╝ ██║████╗  ██║██╔════╝██╔════╝
+ █████╗  ██╔██╗ ██║██║  ███╗██║██╔██╗ ██║█████╗  ███████╗
+ ██╔══╝  ██║╚██╗██║██║   ██║██║██║╚██╗██║██╔══╝  ╚════██║
+ ███████╗██║ ╚████║╚██████╔╝██║██║ ╚████║███████╗███████║
+ ╚══════╝╚═╝  ╚═══╝ ╚═════╝ ╚═╝╚═╝  ╚═══╝╚══════╝╚══════╝
+
+    A client only counts from its second walk on, like a network counter.
+    Work of a client that closed between two walks is lost, it is gone
+    before it could be read again.
+*/
+
+/// Percent of each kind of engine on one GPU, the busiest engine of the kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuLoad {
+    pub busy: f64,
+    pub render: f64,
+    pub video: f64,
+    pub compute: f64,
+}
+
+impl GpuLoad {
+    pub fn get(&self, class: Class) -> f64 {
+        match class {
+            Class::Busy => self.busy,
+            Class::Render => self.render,
+            Class::Video => self.video,
+            Class::Compute => self.compute,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuProcess {
+    pub pid: u32,
+    pub comm: String,
+    /// the index in `devices`
+    pub gpu: usize,
+    /// the busiest engine this process uses, in percent
+    pub busy: f64,
+}
+
+/// busy, total and when they were read
+type Counter = (u64, Option<u64>, Instant);
+/// percent by engine name
+type ByEngine<'a> = HashMap<&'a str, f64>;
+
+pub struct DrmUsage {
+    /// by device, client id and engine
+    last: HashMap<(String, u64, String), Counter>,
+    last_walk: Option<Instant>,
+    /// one per device, in detection order
+    pub gpus: Vec<GpuLoad>,
+    /// the busiest first
+    pub processes: Vec<GpuProcess>,
+}
+
+global!(gpu_usage, DrmUsage, DrmUsage::new());
+
+impl DrmUsage {
+    pub fn new() -> Self {
+        DrmUsage {
+            last: HashMap::new(),
+            last_walk: None,
+            gpus: vec![],
+            processes: vec![],
+        }
+    }
+
+    /// Walks `proc_dir` unless another sensor just did.
+    pub fn refresh(&mut self, proc_dir: &Path, devices: &[String], now: Instant) {
+        let fresh = self
+            .last_walk
+            .is_some_and(|at| now.duration_since(at) < WALK_INTERVAL);
+        if fresh && self.gpus.len() == devices.len() {
+            return;
+        }
+        self.last_walk = Some(now);
+        self.update(&read_clients(proc_dir), devices, now);
+    }
+
+    /// `devices` are PCI addresses, or driver names for GPUs without one.
+    pub fn update(&mut self, clients: &[ProcessClient], devices: &[String], now: Instant) {
+        let mut engines = vec![ByEngine::new(); devices.len()];
+        let mut processes: HashMap<(u32, usize), (&str, ByEngine)> = HashMap::new();
+        let mut seen = HashSet::new();
+        for owned in clients {
+            let client = &owned.client;
+            let Some(gpu) = devices.iter().position(|x| x == client.device()) else {
+                continue;
+            };
+            let process = processes
+                .entry((owned.pid, gpu))
+                .or_insert_with(|| (owned.comm.as_str(), HashMap::new()));
+            for engine in &client.engines {
+                let key = (client.device().to_string(), client.id, engine.name.clone());
+                seen.insert(key.clone());
+                let last = self.last.insert(key, (engine.busy, engine.total, now));
+                let Some((busy, total, at)) = last else {
+                    continue;
+                };
+                let busy = engine.busy.saturating_sub(busy) as f64;
+                let passed = match (engine.total, total) {
+                    (Some(now_total), Some(total)) => now_total.saturating_sub(total) as f64,
+                    _ => now.duration_since(at).as_nanos() as f64,
+                };
+                if passed <= 0.0 {
+                    continue;
+                }
+                let percent = 100.0 * busy / passed / engine.capacity as f64;
+                *engines[gpu].entry(engine.name.as_str()).or_default() += percent;
+                *process.1.entry(engine.name.as_str()).or_default() += percent;
+            }
+        }
+        self.last.retain(|key, _| seen.contains(key));
+
+        let busiest = |engines: &ByEngine, class: Class| {
+            let of_class = engines
+                .iter()
+                .filter(|(name, _)| class == Class::Busy || Class::of(name) == Some(class));
+            of_class.map(|(_, x)| *x).fold(0.0, f64::max).min(100.0)
+        };
+        self.gpus = engines
+            .iter()
+            .map(|engines| GpuLoad {
+                busy: busiest(engines, Class::Busy),
+                render: busiest(engines, Class::Render),
+                video: busiest(engines, Class::Video),
+                compute: busiest(engines, Class::Compute),
+            })
+            .collect();
+        self.processes = processes
+            .into_iter()
+            .map(|((pid, gpu), (comm, engines))| GpuProcess {
+                pid,
+                comm: comm.to_string(),
+                gpu,
+                busy: busiest(&engines, Class::Busy),
+            })
+            .collect();
+        self.processes
+            .sort_by(|a, b| b.busy.total_cmp(&a.busy).then(a.pid.cmp(&b.pid)));
+    }
+}
+
+/// The GPUs with fdinfo engine stats, as `update` matches them.
+pub fn devices(detected: &Detected) -> Vec<String> {
+    let count = detected.get("drm.count").map_or(0, |count| count.to_u64());
+    (0..count)
+        .filter_map(|i| {
+            let driver = detected.get(&format!("drm[{}].driver", i))?.to_string();
+            if !DRIVERS.contains(&driver.as_str()) {
+                return None;
+            }
+            let pdev = detected.get(&format!("drm[{}].pdev", i));
+            Some(pdev.map_or(driver, |pdev| pdev.to_string()))
+        })
+        .collect()
+}
+
+pub struct GpuEngines {
+    class: Class,
+    proc_dir: PathBuf,
+    devices: Vec<String>,
+}
+
+impl GpuEngines {
+    pub fn new(class: Class) -> Self {
+        GpuEngines {
+            class,
+            proc_dir: PathBuf::new(),
+            devices: vec![],
+        }
+    }
+}
+
+impl Sensor for GpuEngines {
+    fn name(&self) -> &str {
+        match self.class {
+            Class::Busy => "gpu.busy",
+            Class::Render => "gpu.render",
+            Class::Video => "gpu.video",
+            Class::Compute => "gpu.compute",
+        }
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.proc_dir = PathBuf::from(detected.file("/proc"));
+        self.devices = devices(detected);
+        // nobody drawing is 0%, not unavailable
+        !self.devices.is_empty() && self.proc_dir.is_dir()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let usage = gpu_usage();
+        usage.refresh(&self.proc_dir, &self.devices, Instant::now());
+        Reading::Values(usage.gpus.iter().map(|gpu| gpu.get(self.class)).collect())
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Percent
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 100.0)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn source(&self) -> String {
+        format!(
+            "{}/*/fdinfo ({})",
+            self.proc_dir.to_string_lossy(),
+            self.devices.join(", ")
+        )
+    }
+}
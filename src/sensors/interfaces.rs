@@ -0,0 +1,266 @@
+use super::rooted;
+use crate::config::NetworkSettings;
+use crate::utils::{early_continue, global};
+use colored::Colorize;
+use std::collections::BTreeSet;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+
+/*
+ ██╗███╗   ██╗████████╗███████╗██████╗ ███████╗ █████╗  ██████╗███████╗███████╗
+ ██║████╗  ██║╚══██╔══╝██╔════╝██╔══██╗██╔════╝██╔══██╗██╔════╝██╔════╝██╔════╝
+ ██║██╔██╗ ██║   ██║   █████╗  ██████╔╝█████╗  ███████║██║     █████╗  ███████╗
+ ██║██║╚██╗██║   ██║   ██╔══╝  ██╔══██╗██╔══╝  ██╔══██║██║     ██╔══╝  ╚════██║
+ ██║██║ ╚████║   ██║   ███████╗██║  ██║██║     ██║  ██║╚██████╗███████╗███████║
+ ╚═╝╚═╝  ╚═══╝   ╚═╝   ╚══════╝╚═╝  ╚═╝╚═╝     ╚═╝  ╚═╝ ╚═════╝╚══════╝╚══════╝
+
+    Every interface in /sys/class/net, rescanned whenever netlink reports
+    a link or address change so USB ethernet, docks and VPNs show up (sysfs
+    has no inotify events for this). Addresses come from procfs, so they
+    work below another root as well.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterfaceKind {
+    Loopback,
+    Ethernet,
+    Wifi,
+    Wwan,
+    /// wireguard, tun and tap
+    Tunnel,
+    Bridge,
+    /// veth, dummy and whatever else has no device behind it
+    Virtual,
+}
+
+impl InterfaceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InterfaceKind::Loopback => "loopback",
+            InterfaceKind::Ethernet => "ethernet",
+            InterfaceKind::Wifi => "wifi",
+            InterfaceKind::Wwan => "wwan",
+            InterfaceKind::Tunnel => "tunnel",
+            InterfaceKind::Bridge => "bridge",
+            InterfaceKind::Virtual => "virtual",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interface {
+    pub name: String,
+    pub kind: InterfaceKind,
+    /// `up`, `down`, `dormant`, ... tunnels usually say `unknown`
+    pub operstate: String,
+    pub ipv4: Vec<Ipv4Addr>,
+    pub ipv6: Vec<Ipv6Addr>,
+    /// Mbit/s, `None` for virtual interfaces and while down
+    pub speed: Option<u64>,
+}
+
+impl Interface {
+    pub fn is_up(&self) -> bool {
+        self.operstate == "up" || self.operstate == "unknown"
+    }
+}
+
+global!(interfaces, Vec<Interface>, vec![]);
+
+/// Rescans below `root` and logs what came and went, true if anything changed.
+pub fn refresh_interfaces(root: &Path) -> bool {
+    let scanned = scan(root);
+    let known = interfaces();
+    if scanned == *known {
+        return false;
+    }
+    let names = |list: &[Interface]| list.iter().map(|i| i.name.clone()).collect::<Vec<_>>();
+    let (before, after) = (names(known), names(&scanned));
+    for name in after.iter().filter(|name| !before.contains(name)) {
+        eprintln!("[{}]: {}({})", "network".green(), "added".yellow(), name);
+    }
+    for name in before.iter().filter(|name| !after.contains(name)) {
+        eprintln!("[{}]: {}({})", "network".green(), "removed".red(), name);
+    }
+    *known = scanned;
+    true
+}
+
+global!(link_events, Option<OwnedFd>, None);
+
+/// A netlink socket that turns readable when a link or an address changes,
+/// `None` where netlink is not allowed (some sandboxes and containers).
+pub fn open_link_events() -> Option<RawFd> {
+    if let Some(socket) = link_events() {
+        return Some(socket.as_raw_fd());
+    }
+    let flags = libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC;
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, flags, libc::NETLINK_ROUTE) };
+    if fd < 0 {
+        return None;
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+    let mut address: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    address.nl_groups =
+        (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+    let size = std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t;
+    let address = &address as *const libc::sockaddr_nl as *const libc::sockaddr;
+    if unsafe { libc::bind(fd, address, size) } != 0 {
+        return None;
+    }
+    Some(link_events().insert(socket).as_raw_fd())
+}
+
+/// Reads every queued message, what they say does not matter, the rescan
+/// after them finds out. False once the socket is broken and got closed.
+pub fn drain_link_events() -> bool {
+    let Some(socket) = link_events() else {
+        return false;
+    };
+    let mut buffer = [0u8; 8192];
+    loop {
+        let pointer = buffer.as_mut_ptr() as *mut libc::c_void;
+        let read = unsafe { libc::recv(socket.as_raw_fd(), pointer, buffer.len(), 0) };
+        early_continue!(read >= 0);
+        match std::io::Error::last_os_error().raw_os_error() {
+            Some(libc::EAGAIN) => return true,
+            // the queue overflowed, the rescan catches up with whatever was lost
+            Some(libc::ENOBUFS | libc::EINTR) => continue,
+            _ => break,
+        }
+    }
+    *link_events() = None;
+    false
+}
+
+/// Up, listed in $config.network.graph (by name or kind) and not in .ignore.
+pub fn graphed<'a>(interfaces: &'a [Interface], settings: &NetworkSettings) -> Vec<&'a Interface> {
+    let matches =
+        |list: &[String], i: &Interface| list.iter().any(|x| *x == i.name || x == i.kind.name());
+    interfaces
+        .iter()
+        .filter(|i| i.is_up() && matches(&settings.graph, i) && !matches(&settings.ignore, i))
+        .collect()
+}
+
+pub fn scan(root: &Path) -> Vec<Interface> {
+    let ipv4 = ipv4_addresses(root);
+    let ipv6 = ipv6_addresses(root);
+    let pattern = rooted(root, "/sys/class/net/*");
+    let dirs = glob::glob(&pattern).unwrap().filter_map(Result::ok);
+    let mut interfaces = vec![];
+    for dir in dirs {
+        let read = |file: &str| {
+            std::fs::read_to_string(dir.join(file))
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
+        let uevent = read("uevent");
+        let devtype = uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("DEVTYPE="))
+            .unwrap_or("");
+        let kind = if read("type") == "772" {
+            InterfaceKind::Loopback
+        } else if dir.join("wireless").exists() || dir.join("phy80211").exists() {
+            InterfaceKind::Wifi
+        } else if devtype == "wwan" {
+            InterfaceKind::Wwan
+        } else if devtype == "wireguard" || dir.join("tun_flags").exists() {
+            InterfaceKind::Tunnel
+        } else if devtype == "bridge" || dir.join("bridge").exists() {
+            InterfaceKind::Bridge
+        } else if dir.join("device").exists() {
+            InterfaceKind::Ethernet
+        } else {
+            InterfaceKind::Virtual
+        };
+        // reading speed fails or says -1 while there is no link
+        let speed = read("speed").parse::<i64>().ok().filter(|speed| *speed > 0);
+        interfaces.push(Interface {
+            kind,
+            operstate: read("operstate"),
+            ipv4: addresses_of(&ipv4, &name),
+            ipv6: addresses_of(&ipv6, &name),
+            speed: speed.map(|speed| speed as u64),
+            name,
+        });
+    }
+    interfaces
+}
+
+fn addresses_of<A: Copy>(addresses: &[(String, A)], name: &str) -> Vec<A> {
+    let matching = addresses.iter().filter(|(iface, _)| iface == name);
+    matching.map(|(_, addr)| *addr).collect()
+}
+
+/*
+  █████╗ ██████╗ ██████╗ ██████╗ ███████╗███████╗███████╗███████╗███████╗
+ ██╔══██╗██╔══██╗██╔══██╗██╔══██╗██╔════╝██╔════╝██╔════╝██╔════╝██╔════╝
+ ███████║██║  ██║██║  ██║██████╔╝█████╗  ███████╗███████╗█████╗  ███████╗
+ ██╔══██║██║  ██║██║  ██║██╔══██╗██╔══╝  ╚════██║╚════██║██╔══╝  ╚════██║
+ ██║  ██║██████╔╝██████╔╝██║  ██║███████╗███████║███████║███████╗███████║
+ ╚═╝  ╚═╝╚═════╝ ╚═════╝ ╚═╝  ╚═╝╚══════╝╚══════╝╚══════╝╚══════╝╚══════╝
+
+    procfs has no list of IPv4 addresses per interface. fib_trie has the
+    local ones (`/32 host LOCAL`), the route whose subnet holds an address
+    says which interface it belongs to.
+*/
+
+fn ipv4_addresses(root: &Path) -> Vec<(String, Ipv4Addr)> {
+    let fib_trie = std::fs::read_to_string(rooted(root, "/proc/net/fib_trie")).unwrap_or_default();
+    let mut local = BTreeSet::new();
+    let mut last = None;
+    for line in fib_trie.lines().map(str::trim) {
+        if let Some(addr) = line.strip_prefix("|-- ") {
+            last = addr.parse::<Ipv4Addr>().ok();
+        } else if line.starts_with("/32 host LOCAL") {
+            local.extend(last);
+        }
+    }
+    // Iface Destination Gateway Flags RefCnt Use Metric Mask ..., the addresses
+    // in network order printed as native integers
+    let route = std::fs::read_to_string(rooted(root, "/proc/net/route")).unwrap_or_default();
+    let hex = |x: &str| u32::from_str_radix(x, 16).ok();
+    let routes = route
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            Some((
+                fields.first()?.to_string(),
+                hex(fields.get(1)?)?,
+                hex(fields.get(7)?)?,
+            ))
+        })
+        .filter(|(_, _, mask)| *mask != 0)
+        .collect::<Vec<_>>();
+    local
+        .into_iter()
+        .filter_map(|addr| {
+            let bits = u32::from_ne_bytes(addr.octets());
+            let (iface, _, _) = routes
+                .iter()
+                .filter(|(_, destination, mask)| bits & mask == *destination)
+                .max_by_key(|(_, _, mask)| mask.count_ones())?;
+            Some((iface.clone(), addr))
+        })
+        .collect()
+}
+
+/// address, ifindex, prefix length, scope, flags, name
+fn ipv6_addresses(root: &Path) -> Vec<(String, Ipv6Addr)> {
+    let if_inet6 = std::fs::read_to_string(rooted(root, "/proc/net/if_inet6")).unwrap_or_default();
+    if_inet6
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let addr = u128::from_str_radix(fields.first()?, 16).ok()?;
+            Some((fields.get(5)?.to_string(), Ipv6Addr::from(addr)))
+        })
+        .collect()
+}
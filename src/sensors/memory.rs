@@ -0,0 +1,284 @@
+use super::{read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/*
+ ███╗   ███╗███████╗███╗   ███╗ ██████╗ ██████╗ ██╗   ██╗
+ ████╗ ████║██╔════╝████╗ ████║██╔═══██╗██╔══██╗╚██╗ ██╔╝
+ ██╔████╔██║█████╗  ██╔████╔██║██║   ██║██████╔╝ ╚████╔╝
+ ██║╚██╔╝██║██╔══╝  ██║╚██╔╝██║██║   ██║██╔══██╗  ╚██╔╝
+ ██║ ╚═╝ ██║███████╗██║ ╚═╝ ██║╚██████╔╝██║  ██║   ██║
+ ╚═╝     ╚═╝╚══════╝╚═╝     ╚═╝ ╚═════╝ ╚═╝  ╚═╝   ╚═╝
+
+    `memory` is what the kernel says is not available (MemAvailable knows
+    which caches can go), `memory.breakdown` splits MemTotal like `free`:
+    apps, reclaimable cache, shared memory (tmpfs, gpu buffers) and free.
+    All in percent of MemTotal, swap in percent of SwapTotal.
+*/
+
+const PROC_MEMINFO: &str = "/proc/meminfo";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Used,
+    Breakdown,
+    Swap,
+    /// memory.current of the user session cgroup
+    Session,
+}
+
+pub struct Memory {
+    kind: Kind,
+    path: String,
+    /// memory.current of the session, `Kind::Session` only
+    session: Option<PathBuf>,
+}
+
+impl Memory {
+    pub fn new(kind: Kind) -> Self {
+        Memory {
+            kind,
+            path: String::new(),
+            session: None,
+        }
+    }
+}
+
+/// The fields of /proc/meminfo in kB, `HugePages_Total` and friends are counts.
+pub fn meminfo(contents: &str) -> HashMap<&str, u64> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let value = value.split_whitespace().next()?.parse().ok()?;
+            Some((key, value))
+        })
+        .collect()
+}
+
+impl Sensor for Memory {
+    fn name(&self) -> &str {
+        match self.kind {
+            Kind::Used => "memory",
+            Kind::Breakdown => "memory.breakdown",
+            Kind::Swap => "memory.swap",
+            Kind::Session => "memory.session",
+        }
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.path = detected.file(PROC_MEMINFO);
+        let Ok(contents) = read_string_from_file_sync(&self.path) else {
+            return false;
+        };
+        match self.kind {
+            // a swap file added later shows up with the next detection
+            Kind::Swap => meminfo(&contents).get("SwapTotal").is_some_and(|x| *x > 0),
+            Kind::Session => {
+                let path = detected.path(self.name(), "memory.session.path");
+                self.session = path.map(PathBuf::from);
+                self.session.as_ref().is_some_and(|path| path.is_file())
+            }
+            Kind::Used | Kind::Breakdown => true,
+        }
+    }
+
+    fn sample(&mut self) -> Reading {
+        let Ok(contents) = read_string_from_file_sync(&self.path) else {
+            return Reading::Unavailable;
+        };
+        let info = meminfo(&contents);
+        let get = |key: &str| info.get(key).copied().unwrap_or(0);
+        let total = get("MemTotal");
+        if total == 0 {
+            return Reading::Unavailable;
+        }
+        let percent = |value: u64| 100.0 * value as f64 / total as f64;
+        match self.kind {
+            // kernels before 3.14 have no MemAvailable
+            Kind::Used => {
+                let available = info.get("MemAvailable").copied().unwrap_or_else(|| {
+                    get("MemFree") + get("Buffers") + get("Cached") + get("SReclaimable")
+                });
+                Reading::Value(percent(total.saturating_sub(available)))
+            }
+            Kind::Breakdown => {
+                let (free, shared) = (get("MemFree"), get("Shmem"));
+                // Shmem is counted in Cached, but it can not be dropped
+                let cache = get("Buffers") + get("Cached") + get("SReclaimable");
+                let apps = total.saturating_sub(free + cache);
+                Reading::Values(vec![
+                    percent(apps),
+                    percent(cache.saturating_sub(shared)),
+                    percent(shared),
+                    percent(free),
+                ])
+            }
+            Kind::Swap => {
+                let swap = get("SwapTotal");
+                if swap == 0 {
+                    // swapoff, until the next detection
+                    return Reading::Unavailable;
+                }
+                let used = swap.saturating_sub(get("SwapFree"));
+                Reading::Value(100.0 * used as f64 / swap as f64)
+            }
+            Kind::Session => {
+                let Some(path) = self.session.as_ref().and_then(|path| path.to_str()) else {
+                    return Reading::Unavailable;
+                };
+                match read_string_from_file_sync(path).map(|x| x.parse::<u64>()) {
+                    // bytes, meminfo is in kB
+                    Ok(Ok(bytes)) => Reading::Value(percent(bytes / 1024)),
+                    _ => Reading::Unavailable,
+                }
+            }
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Percent
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 100.0)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn source(&self) -> String {
+        match &self.session {
+            Some(session) => format!("{}, {}", self.path, session.to_string_lossy()),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/*
+ ███████╗██████╗  █████╗ ███╗   ███╗
+ ╚══███╔╝██╔══██╗██╔══██╗████╗ ████║
+   ███╔╝ ██████╔╝███████║██╔████╔██║
+  ███╔╝  ██╔══██╗██╔══██║██║╚██╔╝██║
+ ███████╗██║  ██║██║  ██║██║ ╚═╝ ██║
+ ╚══════╝╚═╝  ╚═╝╚═╝  ╚═╝╚═╝     ╚═╝
+
+    mm_stat: original size, compressed size, memory used, limit, max used,
+    same pages, compacted pages and huge pages, the sizes in bytes. All
+    zram devices are added up, usually there is just the one for swap.
+*/
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ZramKind {
+    /// what zram takes from RAM, in percent of MemTotal
+    Used,
+    /// original size / compressed size
+    Ratio,
+}
+
+pub struct Zram {
+    kind: ZramKind,
+    meminfo: String,
+    devices: Vec<PathBuf>,
+}
+
+/// Added up over `devices`: original size, compressed size and memory used.
+pub fn read_mm_stat(devices: &[PathBuf]) -> Option<(u64, u64, u64)> {
+    let mut totals = None;
+    for device in devices {
+        let Ok(mm_stat) = read_string_from_file_sync(device.join("mm_stat").to_str()?) else {
+            continue;
+        };
+        let fields = mm_stat
+            .split_whitespace()
+            .filter_map(|x| x.parse::<u64>().ok())
+            .collect::<Vec<_>>();
+        let [original, compressed, used, ..] = fields[..] else {
+            continue;
+        };
+        let (o, c, u) = totals.unwrap_or((0, 0, 0));
+        totals = Some((o + original, c + compressed, u + used));
+    }
+    totals
+}
+
+impl Zram {
+    pub fn new(kind: ZramKind) -> Self {
+        Zram {
+            kind,
+            meminfo: String::new(),
+            devices: vec![],
+        }
+    }
+}
+
+impl Sensor for Zram {
+    fn name(&self) -> &str {
+        match self.kind {
+            ZramKind::Used => "zram.used",
+            ZramKind::Ratio => "zram.ratio",
+        }
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.meminfo = detected.file(PROC_MEMINFO);
+        self.devices = (0..detected.get("zram.count").map_or(0, |count| count.to_u64()))
+            .filter_map(|i| detected.get(&format!("zram[{}].path", i)))
+            .map(|path| PathBuf::from(path.to_string()))
+            .collect();
+        read_mm_stat(&self.devices).is_some()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let Some((original, compressed, used)) = read_mm_stat(&self.devices) else {
+            return Reading::Unavailable;
+        };
+        match self.kind {
+            ZramKind::Used => {
+                let contents = read_string_from_file_sync(&self.meminfo).unwrap_or_default();
+                let total = meminfo(&contents).get("MemTotal").copied().unwrap_or(0);
+                match total {
+                    0 => Reading::Unavailable,
+                    total => Reading::Value(100.0 * used as f64 / 1024.0 / total as f64),
+                }
+            }
+            // an empty zram device compresses nothing
+            ZramKind::Ratio if compressed == 0 => Reading::Values(vec![]),
+            ZramKind::Ratio => {
+                let ratio = original as f64 / compressed as f64;
+                Reading::Value((ratio * 100.0).round() / 100.0)
+            }
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        match self.kind {
+            ZramKind::Used => SensorUnit::Percent,
+            ZramKind::Ratio => SensorUnit::Raw,
+        }
+    }
+
+    fn range(&self) -> (f64, f64) {
+        match self.kind {
+            ZramKind::Used => (0.0, 100.0),
+            // lz4 gets around 2-3, zstd 3-4
+            ZramKind::Ratio => (1.0, 5.0),
+        }
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn source(&self) -> String {
+        let devices = self
+            .devices
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>();
+        devices.join(", ")
+    }
+}
@@ -1,4 +1,20 @@
+mod ac;
+mod battery;
+mod cpu;
+mod cpufreq;
 pub mod detect;
+mod disk;
+pub mod drm;
+pub mod interfaces;
+mod memory;
+mod network;
+mod pci;
+pub mod power;
+mod pressure;
+pub mod processes;
+mod registry;
+pub mod scale;
+mod sysfs;
 
 /*
  ███████╗███████╗███╗   ██╗███████╗ ██████╗ ██████╗ ███████╗
@@ -7,63 +23,169 @@ pub mod detect;
  ╚════██║██╔══╝  ██║╚██╗██║╚════██║██║   ██║██╔══██╗╚════██║
  ███████║███████╗██║ ╚████║███████║╚██████╔╝██║  ██║███████║
  ╚══════╝╚══════╝╚═╝  ╚═══╝╚══════╝ ╚═════╝ ╚═╝  ╚═╝╚══════╝
+
+    Every sensor implements `Sensor` and lives in the registry under a
+    stable name (`cpu.temp`, `battery.capacity`, ...). That name is what
+    $config.sensors, the uniform writer and the widgets use.
 */
 
-use crate::utils::global;
-use chrono::Local;
-use chrono::Timelike;
+use crate::config::{DiskSettings, SensorUnit};
 use colored::Colorize;
+use detect::Value;
 use glib::spawn_future_local;
 use glib::timeout_future;
-use std::cmp::max;
+use glib::{unix_fd_add_local, ControlFlow, IOCondition};
 use std::collections::HashMap;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub use registry::{registry, Entry, Registry};
+
+#[cfg(test)]
+mod tests;
+
+const TICK: Duration = Duration::from_millis(1000 / 30);
+const REDETECT_INTERVAL: Duration = Duration::from_secs(60);
+const INTERFACES_INTERVAL: Duration = Duration::from_secs(2);
+
+/*
+ ████████╗██████╗  █████╗ ██╗████████╗
+ ╚══██╔══╝██╔══██╗██╔══██╗██║╚══██╔══╝
+    ██║   ██████╔╝███████║██║   ██║
+    ██║   ██╔══██╗██╔══██║██║   ██║
+    ██║   ██║  ██║██║  ██║██║   ██║
+    ╚═╝   ╚═╝  ╚═╝╚═╝  ╚═╝╚═╝   ╚═╝
+*/
+
+pub trait Sensor {
+    /// The stable name, e.g. `cpu.temp`.
+    fn name(&self) -> &str;
+    /// Picks what to read from the detection results,
+    /// false if there is nothing on this machine.
+    fn detect(&mut self, detected: &Detected) -> bool;
+    /// A reading in `unit()`, `Reading::Unavailable` if the hardware went away.
+    fn sample(&mut self) -> Reading;
+    fn unit(&self) -> SensorUnit;
+    /// The default min and max of `sample()`, $config.sensors can override both.
+    fn range(&self) -> (f64, f64);
+    fn poll_interval(&self) -> Duration;
+    /// Where the values come from, for `shaderbar list-sensors`.
+    fn source(&self) -> String;
+    /// Shown instead of the value, for readings that stand for a name like the governor.
+    fn text(&self) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reading {
+    Unavailable,
+    Value(f64),
+    /// One value per core, interface, ...
+    Values(Vec<f64>),
+}
+
+impl Reading {
+    pub fn values(&self) -> Vec<f64> {
+        match self {
+            Reading::Unavailable => vec![],
+            Reading::Value(value) => vec![*value],
+            Reading::Values(values) => values.clone(),
+        }
+    }
+}
+
+/// The results of `detect::detect_sensors`, with $config.sensors.<name>.path
+/// and the disks $config.disk asks for on top.
+pub struct Detected {
+    root: PathBuf,
+    found: HashMap<String, Value>,
+    configured: HashMap<String, String>,
+    disk: DiskSettings,
+}
+
+impl Detected {
+    pub async fn detect() -> Detected {
+        let mut detected = Detected::detect_in(&root()).await;
+        let settings = &crate::config::config().settings;
+        detected.configured = settings
+            .sensors
+            .iter()
+            .filter_map(|(name, settings)| Some((name.clone(), settings.path.clone()?)))
+            .collect();
+        detected.disk = settings.disk.clone();
+        detected
+    }
+
+    /// Detection below another root, with the default config.
+    pub async fn detect_in(root: &Path) -> Detected {
+        Detected {
+            root: root.to_path_buf(),
+            found: detect::detect_sensors(root).await,
+            configured: HashMap::new(),
+            disk: DiskSettings::default(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.found.get(key)
+    }
+
+    pub fn found(&self) -> &HashMap<String, Value> {
+        &self.found
+    }
+
+    /// The configured path of sensor `name`, otherwise what detection found as `key`.
+    /// Configured paths are taken as they are, they do not move with the root.
+    pub fn path(&self, name: &str, key: &str) -> Option<String> {
+        let configured = self.configured.get(name).cloned();
+        configured.or_else(|| self.get(key).map(|value| value.to_string()))
+    }
+
+    /// $config.sensors.<name>.path
+    pub fn configured(&self, name: &str) -> Option<&str> {
+        self.configured.get(name).map(|path| path.as_str())
+    }
+
+    /// $config.disk
+    pub fn disk(&self) -> &DiskSettings {
+        &self.disk
+    }
+
+    /// `path` below the root, e.g. `/proc/stat`.
+    pub fn file(&self, path: &str) -> String {
+        rooted(&self.root, path)
+    }
+}
 
 /*
- ███████╗████████╗██████╗ ██╗   ██╗ ██████╗████████╗
- ██╔════╝╚══██╔══╝██╔══██╗██║   ██║██╔════╝╚══██╔══╝
- ███████╗   ██║   ██████╔╝██║   ██║██║        ██║
- ╚════██║   ██║   ██╔══██╗██║   ██║██║        ██║
- ███████║   ██║   ██║  ██║╚██████╔╝╚██████╗   ██║
- ╚══════╝   ╚═╝   ╚═╝  ╚═╝ ╚═════╝  ╚═════╝   ╚═╝
+ ██████╗  ██████╗  ██████╗ ████████╗
+ ██╔══██╗██╔═══██╗██╔═══██╗╚══██╔══╝
+ ██████╔╝██║   ██║██║   ██║   ██║
+ ██╔══██╗██║   ██║██║   ██║   ██║
+ ██║  ██║╚██████╔╝╚██████╔╝   ██║
+ ╚═╝  ╚═╝ ╚═════╝  ╚═════╝    ╚═╝
+
+    /sys and /proc are read below `--root` or $SHADERBAR_ROOT, so a tree
+    copied from another machine (see tests/fixtures) can stand in for this one.
 */
 
-global!(sensors, Sensors, Sensors::new());
-
-#[derive(Debug, Clone)]
-pub struct Sensors {
-    pub hour: u8,
-    pub minute: u8,
-    pub second: u8,
-    pub bat_ac_connected_path: String,
-    pub bat_ac_connected: u8,
-    pub bat_capacity_path: String,
-    pub bat_status_path: String,
-    pub bat_status: u8,
-    pub bat: u8,
-    pub cpu_count: u8,
-    pub cpu_fan_path: String,
-    pub cpu_fan: u8,
-    pub cpu_last_idle: Vec<u64>,
-    pub cpu_last_total: Vec<u64>,
-    pub cpu_load: Vec<u8>,
-    pub cpu_temp_path: String,
-    pub cpu_temp: u8,
-    pub gpu_load: Vec<u8>,
-    pub gpu_load_path: String,
-    pub gpu_fan_path: String,
-    pub gpu_fan: u8,
-    pub gpu_temp_path: String,
-    pub gpu_temp: u8,
-    pub mem: Vec<u8>,
-    pub net_allowed: std::collections::HashMap<String, NetworkInterface>,
-    pub net_count: u8,
-    pub net_last_rx: Vec<u64>,
-    pub net_last_tx: Vec<u64>,
-    pub net_max_rx: Vec<u64>,
-    pub net_max_tx: Vec<u64>,
-    pub net_rx: Vec<u8>,
-    pub net_tx: Vec<u8>,
+pub const ROOT_VAR: &str = "SHADERBAR_ROOT";
+
+pub fn root() -> PathBuf {
+    if let Some(root) = &crate::cli::args().root {
+        return root.clone();
+    }
+    match std::env::var_os(ROOT_VAR) {
+        Some(root) if !root.is_empty() => PathBuf::from(root),
+        _ => PathBuf::from("/"),
+    }
+}
+
+pub fn rooted(root: &Path, path: &str) -> String {
+    root.join(path.trim_start_matches('/'))
+        .to_string_lossy()
+        .to_string()
 }
 
 /*
@@ -75,22 +197,67 @@ pub struct Sensors {
     ╚═╝   ╚═╝  ╚═╝╚═╝  ╚═╝╚══════╝╚═╝  ╚═╝╚═════╝ ╚══════╝
 */
 
-pub fn spawn_read_sensors() {
-    sensors().read();
+/// Samples whatever is due (see `Sensor::poll_interval`) and pushes it to the uniforms.
+pub fn spawn_sample_sensors() {
+    registry().sample(Instant::now());
+    power::update_power_source();
     spawn_future_local(async move {
         loop {
-            sensors().read();
-            timeout_future(Duration::from_millis(1000 / 30)).await;
+            timeout_future(TICK).await;
+            registry().sample(Instant::now());
+            power::update_power_source();
+            crate::gl::uniform::update_uniforms();
         }
     });
 }
 
-pub fn spawn_read_sensors_lowfreq() {
-    sensors().read_lowfreq();
+/// Sensors can show up late (a docked laptop, a module loaded after login),
+/// so detection runs again for whatever is still missing.
+pub fn spawn_redetect_sensors() {
     spawn_future_local(async move {
         loop {
-            timeout_future(Duration::from_secs(1)).await;
-            sensors().read_lowfreq();
+            timeout_future(REDETECT_INTERVAL).await;
+            if registry().is_incomplete() {
+                detect_sensors(Registry::detect_missing).await;
+            }
+        }
+    });
+}
+
+/// Network interfaces come and go far more often than anything else
+/// (docks, VPNs, tethering), so netlink says when to rescan. Another root
+/// has no events to go with it, that and a failing socket fall back to a
+/// rescan every few seconds.
+pub fn spawn_watch_interfaces() {
+    let root = root();
+    let events = match root == Path::new("/") {
+        true => interfaces::open_link_events(),
+        false => None,
+    };
+    let Some(fd) = events else {
+        spawn_poll_interfaces();
+        return;
+    };
+    unix_fd_add_local(fd, IOCondition::IN, move |_, _| {
+        if interfaces::drain_link_events() {
+            interfaces::refresh_interfaces(&root);
+            return ControlFlow::Continue;
+        }
+        eprintln!(
+            "[{}]: {}",
+            "network".green(),
+            "netlink failed, polling instead".red()
+        );
+        spawn_poll_interfaces();
+        ControlFlow::Break
+    });
+}
+
+fn spawn_poll_interfaces() {
+    spawn_future_local(async move {
+        loop {
+            timeout_future(INTERFACES_INTERVAL).await;
+            interfaces::refresh_interfaces(&root());
         }
     });
 }
@@ -105,304 +272,21 @@ pub fn spawn_read_sensors_lowfreq() {
 */
 
 pub async fn detect() {
-    let detected = detect::detect_sensors().await;
-    let sensors = sensors();
-    for (k, v) in detected.iter() {
-        match k.as_str() {
-            "cpu.temp" => sensors.cpu_temp_path = v.to_string(),
-            "gpu.temp" => sensors.gpu_temp_path = v.to_string(),
-            "cpu.fan" => sensors.cpu_fan_path = v.to_string(),
-            "gpu.fan" => sensors.gpu_fan_path = v.to_string(),
-            "gpu[0].usage" => {
-                let count = detected.get("gpu.count").unwrap().to_u64();
-                sensors.gpu_load_path = v.to_string();
-                sensors.gpu_load = vec![0u8; count as usize];
-            }
-            "ethernet.interface" => {
-                let iface = detected.get("ethernet.interface").unwrap().to_string();
-                sensors.net_allowed.insert(
-                    iface,
-                    NetworkInterface {
-                        type_: "ethernet".to_string(),
-                        icon: "".to_string(),
-                    },
-                );
-            }
-            "wifi.interface" => {
-                let iface = detected.get("wifi.interface").unwrap().to_string();
-                sensors.net_allowed.insert(
-                    iface,
-                    NetworkInterface {
-                        type_: "wifi".to_string(),
-                        icon: "".to_string(),
-                    },
-                );
-            }
-            "battery.capacity" => {
-                sensors.bat_capacity_path = detected.get("battery.capacity").unwrap().to_string();
-            }
-            "battery.status" => {
-                sensors.bat_status_path = detected.get("battery.status").unwrap().to_string();
-            }
-            _ => {}
-        }
-    }
-    eprintln!(
-        "[{}]: {}\n{:?}",
-        "sensors".green(),
-        "detected".yellow(),
-        sensors
-    );
+    detect_sensors(Registry::detect).await;
 }
 
-/*
- ███████╗███████╗███╗   ██╗███████╗ ██████╗ ██████╗ ███████╗
- ██╔════╝██╔════╝████╗  ██║██╔════╝██╔═══██╗██╔══██╗██╔════╝
- ███████╗█████╗  ██╔██╗ ██║███████╗██║   ██║██████╔╝███████╗
- ╚════██║██╔══╝  ██║╚██╗██║╚════██║██║   ██║██╔══██╗╚════██║
- ███████║███████╗██║ ╚████║███████║╚██████╔╝██║  ██║███████║
- ╚══════╝╚══════╝╚═╝  ╚═══╝╚══════╝ ╚═════╝ ╚═╝  ╚═╝╚══════╝
-*/
-
-impl Sensors {
-    pub fn new() -> Self {
-        Sensors {
-            hour: 0,
-            minute: 0,
-            second: 0,
-            bat_ac_connected_path: "".to_string(),
-            bat_ac_connected: 0,
-            bat_capacity_path: "".to_string(),
-            bat_status_path: "".to_string(),
-            bat_status: 0,
-            bat: 0,
-            cpu_count: 0,
-            cpu_fan_path: "".to_string(),
-            cpu_fan: 0,
-            cpu_last_idle: vec![0u64; 0],
-            cpu_last_total: vec![0u64; 0],
-            cpu_load: vec![0u8; 0],
-            cpu_temp_path: "".to_string(),
-            cpu_temp: 0,
-            gpu_load: vec![0u8; 0],
-            gpu_load_path: "".to_string(),
-            gpu_fan_path: "".to_string(),
-            gpu_fan: 0,
-            gpu_temp_path: "".to_string(),
-            gpu_temp: 0,
-            mem: vec![0u8; 4],
-            net_allowed: HashMap::new(),
-            net_count: 0,
-            net_last_rx: vec![0u64; 0],
-            net_last_tx: vec![0u64; 0],
-            net_max_rx: vec![0u64; 0],
-            net_max_tx: vec![0u64; 0],
-            net_rx: vec![0u8; 0],
-            net_tx: vec![0u8; 0],
-        }
-    }
-
-    /*
-     ██████╗ ███████╗ █████╗ ██████╗
-     ██╔══██╗██╔════╝██╔══██╗██╔══██╗
-     ██████╔╝█████╗  ███████║██║  ██║
-     ██╔══██╗██╔══╝  ██╔══██║██║  ██║
-     ██║  ██║███████╗██║  ██║██████╔╝
-     ╚═╝  ╚═╝╚══════╝╚═╝  ╚═╝╚═════╝
-    */
-
-    pub fn read_lowfreq(&mut self) {
-        let cpu_fan = read_number_from_file_sync(&self.cpu_fan_path).unwrap();
-        let gpu_fan = read_number_from_file_sync(&self.gpu_fan_path).unwrap();
-        let cpu_temp = read_number_from_file_sync(&self.cpu_temp_path).unwrap();
-        let gpu_temp = read_number_from_file_sync(&self.gpu_temp_path).unwrap();
-        let battery_percentage =
-            read_number_from_file_sync("/sys/class/power_supply/BAT0/capacity").unwrap();
-        let battery_status = read_string_from_file_sync(self.bat_status_path.as_str()).unwrap();
-        self.gpu_load = vec![read_number_from_file_sync(&self.gpu_load_path).unwrap() as u8];
-        self.cpu_temp = (255 * cpu_temp / 1000) as u8;
-        self.gpu_temp = (255 * gpu_temp / 1000) as u8;
-        self.cpu_fan = (255 * cpu_fan / 5000) as u8;
-        self.gpu_fan = (255 * gpu_fan / 5000) as u8;
-        self.bat = (255 * battery_percentage / 110) as u8;
-        self.bat_status = if battery_status == "Charging" { 1 } else { 0 };
-    }
-
-    pub fn read(&mut self) {
-        let cpu_load = read_string_from_file_sync("/proc/stat").unwrap();
-        let net_load = read_string_from_file_sync("/proc/net/dev").unwrap();
-        let mem_load = read_string_from_file_sync("/proc/meminfo").unwrap();
-        let now = Local::now();
-        self.hour = now.hour() as u8;
-        self.minute = now.minute() as u8;
-        self.second = now.second() as u8;
-        self.read_cpu(cpu_load);
-        self.read_network(net_load);
-        self.read_memory(mem_load);
-        crate::gl::uniform::update_uniforms();
-    }
-
-    /*
-      ██████╗██████╗ ██╗   ██╗
-     ██╔════╝██╔══██╗██║   ██║
-     ██║     ██████╔╝██║   ██║
-     ██║     ██╔═══╝ ██║   ██║
-     ╚██████╗██║     ╚██████╔╝
-      ╚═════╝╚═╝      ╚═════╝
-    */
-
-    fn read_cpu(&mut self, contents: String) {
-        let lines = contents.lines();
-        let len = lines.count();
-        if self.cpu_load.len() < len {
-            self.cpu_load.resize(len, 0);
-            self.cpu_last_idle.resize(len, 0);
-            self.cpu_last_total.resize(len, 0);
-        }
-        let mut is_first = true;
-        let mut cpuid: usize = 0;
-        for line in contents.lines() {
-            if !line.starts_with("cpu") {
-                continue;
-            }
-            if is_first {
-                is_first = false;
-                continue;
-            }
-            let cpu: Vec<u64> = line
-                .split_whitespace()
-                .skip(1)
-                .map(|s| s.parse().unwrap())
-                .collect();
-            let user = cpu[0];
-            let nice = cpu[1];
-            let system = cpu[2];
-            let idle = cpu[3];
-            let iowait = cpu[4];
-            let irq = cpu[5];
-            let softirq = cpu[6];
-            let steal = cpu[7];
-            let guest = cpu[8];
-            let guest_nice = cpu[9];
-            let total =
-                user + nice + system + idle + iowait + irq + softirq + steal + guest + guest_nice;
-            let idle = idle + iowait;
-            let last_total = self.cpu_last_total[cpuid];
-            let last_idle = self.cpu_last_idle[cpuid];
-            let relative_total = total - last_total;
-            let relative_idle = idle - last_idle;
-            let usage = if relative_total > 0 {
-                255 * (relative_total - relative_idle) / relative_total
-            } else {
-                0
-            };
-            self.cpu_last_total[cpuid] = total;
-            self.cpu_last_idle[cpuid] = idle;
-            cpuid += 1;
-            self.cpu_load[cpuid] = usage as u8;
-        }
-        self.cpu_count = cpuid as u8;
-        if self.cpu_load.len() != cpuid {
-            self.cpu_load.resize(cpuid, 0);
-            self.cpu_last_idle.resize(cpuid, 0);
-            self.cpu_last_total.resize(cpuid, 0);
-        }
-    }
-
-    /*
-     ███╗   ██╗███████╗████████╗
-     ████╗  ██║██╔════╝╚══██╔══╝
-     ██╔██╗ ██║█████╗     ██║
-     ██║╚██╗██║██╔══╝     ██║
-     ██║ ╚████║███████╗   ██║
-     ╚═╝  ╚═══╝╚══════╝   ╚═╝
-    */
-
-    fn read_network(&mut self, contents: String) {
-        let mut i = 0;
-        let lines = contents.lines();
-        let len = lines.count();
-        if self.net_rx.len() < len {
-            self.net_rx.resize(len, 0);
-            self.net_tx.resize(len, 0);
-            self.net_last_rx.resize(len, 0);
-            self.net_last_tx.resize(len, 0);
-            self.net_max_rx.resize(len, 0);
-            self.net_max_tx.resize(len, 0);
-        }
-        for line in contents.lines() {
-            let mut parts = line.split_whitespace();
-            let interface = parts.next().unwrap().trim_end_matches(':');
-            let config = self.net_allowed.get(interface);
-            if !config.is_some() {
-                continue;
-            }
-            let rx = parts.next().unwrap().parse::<u64>().unwrap();
-            let tx = parts.next().unwrap().parse::<u64>().unwrap();
-            let last_rx = self.net_last_rx[i];
-            let last_tx = self.net_last_tx[i];
-            let relative_rx = rx - last_rx;
-            let relative_tx = tx - last_tx;
-            let max_rx = max(self.net_max_rx[i], relative_rx);
-            let max_tx = max(self.net_max_tx[i], relative_tx);
-            self.net_max_rx[i] = max_rx;
-            self.net_max_tx[i] = max_tx;
-            if max_rx == 0 {
-                self.net_rx[i] = 0;
-            } else {
-                self.net_rx[i] = (255 * relative_rx / max_rx) as u8;
-            }
-            if max_tx == 0 {
-                self.net_tx[i] = 0;
-            } else {
-                self.net_tx[i] = (255 * relative_tx / max_tx) as u8;
-            }
-            i += 1;
-        }
-        self.net_count = i as u8;
-        if self.net_rx.len() != i {
-            self.net_rx.resize(i, 0);
-            self.net_tx.resize(i, 0);
-            self.net_last_rx.resize(i, 0);
-            self.net_last_tx.resize(i, 0);
-            self.net_max_rx.resize(i, 0);
-            self.net_max_tx.resize(i, 0);
-        }
-    }
-
-    /*
-     ███╗   ███╗███████╗███╗   ███╗
-     ████╗ ████║██╔════╝████╗ ████║
-     ██╔████╔██║█████╗  ██╔████╔██║
-     ██║╚██╔╝██║██╔══╝  ██║╚██╔╝██║
-     ██║ ╚═╝ ██║███████╗██║ ╚═╝ ██║
-     ╚═╝     ╚═╝╚══════╝╚═╝     ╚═╝
-    */
-
-    fn read_memory(&mut self, contents: String) {
-        let mut total = 0;
-        let mut free = 0;
-        let mut buffers = 0;
-        let mut cached = 0;
-        for line in contents.lines() {
-            let mut parts = line.split_whitespace();
-            let key = parts.next().unwrap();
-            let value = parts.next().unwrap().parse::<u64>().unwrap();
-            match key {
-                "MemTotal:" => total = value,
-                "MemFree:" => free = value,
-                "Buffers:" => buffers = value,
-                "Cached:" => cached = value,
-                _ => {}
-            }
-        }
-        let used = total - free - buffers - cached;
-        self.mem = vec![
-            (255 * used / total) as u8,
-            (255 * buffers / total) as u8,
-            (255 * cached / total) as u8,
-            (255 * free / total) as u8,
-        ];
+async fn detect_sensors(detect: fn(&mut Registry, &Detected) -> bool) {
+    interfaces::refresh_interfaces(&root());
+    let detected = Detected::detect().await;
+    let registry = registry();
+    // re-detection runs periodically, only changes are worth a log line
+    if detect(registry, &detected) {
+        eprintln!(
+            "[{}]: {}({})",
+            "sensors".green(),
+            "detected".yellow(),
+            registry.available().join(", ")
+        );
     }
 }
 
@@ -417,16 +301,13 @@ impl Sensors {
 
 pub fn read_number_from_file_sync(path: &str) -> Result<u64, std::io::Error> {
     let contents = std::fs::read_to_string(path)?;
-    Ok(contents.trim().parse().unwrap())
+    contents
+        .trim()
+        .parse()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
 }
 
 pub fn read_string_from_file_sync(path: &str) -> Result<String, std::io::Error> {
     let contents = std::fs::read_to_string(path)?;
     Ok(contents.trim().to_string())
 }
-
-#[derive(Debug, Clone)]
-pub struct NetworkInterface {
-    pub type_: String,
-    pub icon: String,
-}
@@ -0,0 +1,235 @@
+use super::interfaces::{graphed, interfaces, Interface};
+use super::{read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::{config, NetworkScale, NetworkSettings, SensorUnit};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/*
+ ███╗   ██╗███████╗████████╗
+ ████╗  ██║██╔════╝╚══██╔══╝
+ ██╔██╗ ██║█████╗     ██║
+ ██║╚██╗██║██╔══╝     ██║
+ ██║ ╚████║███████╗   ██║
+ ╚═╝  ╚═══╝╚══════╝   ╚═╝
+
+    Bytes per second for each graphed interface (see $config.network) from
+    /proc/net/dev. Interfaces come and go, so the counters are kept by name.
+    The full scale follows $config.network.scale, see `Peak`.
+*/
+
+const PROC_NET_DEV: &str = "/proc/net/dev";
+/// Mbit/s in bytes/s
+const MBIT: f64 = 125_000.0;
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Rx,
+    Tx,
+}
+
+pub struct NetworkLoad {
+    direction: Direction,
+    path: String,
+    interfaces: Vec<String>,
+    /// the last counter of each interface and when it was read
+    counters: HashMap<String, (u64, Instant)>,
+    peak: Peak,
+    /// bytes/s that fill the graph, the upper end of `range()`
+    full_scale: f64,
+}
+
+impl NetworkLoad {
+    pub fn rx() -> Self {
+        NetworkLoad::new(Direction::Rx)
+    }
+
+    pub fn tx() -> Self {
+        NetworkLoad::new(Direction::Tx)
+    }
+
+    fn new(direction: Direction) -> Self {
+        NetworkLoad {
+            direction,
+            path: String::new(),
+            interfaces: vec![],
+            counters: HashMap::new(),
+            peak: Peak::new(),
+            full_scale: NetworkSettings::default().min_peak,
+        }
+    }
+
+    /// Bytes/s for each of `interfaces`, 0 for the first reading and after a reset.
+    pub fn rates(
+        &mut self,
+        contents: &str,
+        interfaces: &[&Interface],
+        now: Instant,
+        settings: &NetworkSettings,
+    ) -> Vec<f64> {
+        // rx bytes are the first column, tx bytes the ninth
+        let column = match self.direction {
+            Direction::Rx => 0,
+            Direction::Tx => 8,
+        };
+        let mut rates = vec![0.0; interfaces.len()];
+        for line in contents.lines() {
+            let Some((name, counters)) = line.split_once(':') else {
+                continue;
+            };
+            let name = name.trim();
+            let Some(i) = interfaces.iter().position(|x| x.name == name) else {
+                continue;
+            };
+            let Some(bytes) = counters
+                .split_whitespace()
+                .nth(column)
+                .and_then(|x| x.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            // the first step would be everything since boot
+            let Some((last, at)) = self.counters.insert(name.to_string(), (bytes, now)) else {
+                continue;
+            };
+            let seconds = now.duration_since(at).as_secs_f64();
+            if let (Some(delta), true) = (counter_delta(last, bytes), seconds > 0.0) {
+                rates[i] = delta as f64 / seconds;
+            }
+        }
+        // a removed interface starts over when it comes back
+        self.counters
+            .retain(|name, _| interfaces.iter().any(|x| x.name == *name));
+        let busiest = rates.iter().copied().fold(0.0, f64::max);
+        let peak = self
+            .peak
+            .update(busiest, now, settings.scale, settings.peak_seconds);
+        let peak = peak.max(settings.min_peak);
+        let link = interfaces.iter().filter_map(|x| x.speed).max();
+        self.full_scale = match (settings.scale, link) {
+            (NetworkScale::Link, Some(speed)) => speed as f64 * MBIT,
+            // wifi and tunnels have no speed, they fall back to the peak
+            _ => peak,
+        };
+        rates
+    }
+}
+
+/// Bytes between two readings of a counter. Counters run backwards when the
+/// driver resets them or the interface is recreated under the same name,
+/// that is `None`. Only a 32 bit counter close to its end can have wrapped.
+pub fn counter_delta(last: u64, bytes: u64) -> Option<u64> {
+    const WRAP: u64 = 1 << 32;
+    if bytes >= last {
+        return Some(bytes - last);
+    }
+    let wrapped = (WRAP / 2..WRAP).contains(&last) && bytes < WRAP / 2;
+    wrapped.then(|| WRAP - last + bytes)
+}
+
+impl Sensor for NetworkLoad {
+    fn name(&self) -> &str {
+        match self.direction {
+            Direction::Rx => "network.rx",
+            Direction::Tx => "network.tx",
+        }
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        let path = detected.file(PROC_NET_DEV);
+        if path != self.path {
+            self.path = path;
+            self.counters.clear();
+        }
+        // which interfaces is decided on every sample, they come and go
+        read_string_from_file_sync(&self.path).is_ok()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let Ok(contents) = read_string_from_file_sync(&self.path) else {
+            return Reading::Unavailable;
+        };
+        let settings = &config().settings.network;
+        let interfaces = graphed(interfaces(), settings);
+        let rates = self.rates(&contents, &interfaces, Instant::now(), settings);
+        self.interfaces = interfaces.iter().map(|x| x.name.clone()).collect();
+        Reading::Values(rates)
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Bytes
+    }
+
+    /// Moves with the traffic, a `max` in $config.sensors pins it.
+    fn range(&self) -> (f64, f64) {
+        (0.0, self.full_scale)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(1000 / 30)
+    }
+
+    fn source(&self) -> String {
+        format!("{} ({})", self.path, self.interfaces.join(", "))
+    }
+}
+
+/*
+ ██████╗ ███████╗ █████╗ ██╗  ██╗
+ ██╔══██╗██╔════╝██╔══██╗██║ ██╔╝
+ ██████╔╝█████╗  ███████║█████╔╝
+ ██╔═══╝ ██╔══╝  ██╔══██║██╔═██╗
+ ██║     ███████╗██║  ██║██║  ██╗
+ ╚═╝     ╚══════╝╚═╝  ╚═╝╚═╝  ╚═╝
+
+    The biggest rate seen lately. `decay` halves an old peak every
+    `peak_seconds`, `window` forgets it after `peak_seconds`. Either way
+    one burst no longer flattens the graph for good.
+*/
+
+pub struct Peak {
+    value: f64,
+    at: Option<Instant>,
+    /// rates of the window, falling from front to back
+    window: VecDeque<(Instant, f64)>,
+}
+
+impl Peak {
+    pub fn new() -> Self {
+        Peak {
+            value: 0.0,
+            at: None,
+            window: VecDeque::new(),
+        }
+    }
+
+    /// The peak including `rate`, `link` decays like `decay`.
+    pub fn update(&mut self, rate: f64, now: Instant, scale: NetworkScale, seconds: f64) -> f64 {
+        match scale {
+            NetworkScale::Window => {
+                // a rate hides every smaller one before it, they can never be the peak again
+                while self.window.back().is_some_and(|(_, x)| *x <= rate) {
+                    self.window.pop_back();
+                }
+                self.window.push_back((now, rate));
+                let length = Duration::from_secs_f64(seconds);
+                while self
+                    .window
+                    .front()
+                    .is_some_and(|(at, _)| now.duration_since(*at) > length)
+                {
+                    self.window.pop_front();
+                }
+                self.value = self.window.front().map_or(rate, |(_, x)| *x);
+            }
+            NetworkScale::Decay | NetworkScale::Link => {
+                let elapsed = self
+                    .at
+                    .map_or(0.0, |at| now.duration_since(at).as_secs_f64());
+                let decayed = self.value * 0.5f64.powf(elapsed / seconds);
+                self.value = decayed.max(rate);
+            }
+        }
+        self.at = Some(now);
+        self.value
+    }
+}
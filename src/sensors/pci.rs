@@ -0,0 +1,59 @@
+use super::rooted;
+use std::path::Path;
+
+/*
+ ██████╗  ██████╗██╗
+ ██╔══██╗██╔════╝██║
+ ██████╔╝██║     ██║
+ ██╔═══╝ ██║     ██║
+ ██║     ╚██████╗██║
+ ╚═╝      ╚═════╝╚═╝
+
+    Device names from pci.ids, what `udevadm` calls ID_MODEL_FROM_DATABASE.
+    The file is read below the root too, so fixtures can bring their own.
+*/
+
+const PCI_IDS: &[&str] = &[
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+];
+
+/// The name of the pci device in `device_dir` (the one with `vendor` and `device` in it).
+pub fn model(root: &Path, device_dir: &Path) -> Option<String> {
+    let id = |file: &str| {
+        let text = std::fs::read_to_string(device_dir.join(file)).ok()?;
+        Some(text.trim().trim_start_matches("0x").to_lowercase())
+    };
+    let (vendor, device) = (id("vendor")?, id("device")?);
+    let ids = PCI_IDS
+        .iter()
+        .find_map(|path| std::fs::read_to_string(rooted(root, path)).ok())?;
+    lookup(&ids, &vendor, &device)
+}
+
+/// Vendors start at the line start, their devices are indented by one tab
+/// and subsystems by two. Comments start with `#`.
+fn lookup(ids: &str, vendor: &str, device: &str) -> Option<String> {
+    let mut in_vendor = false;
+    for line in ids.lines() {
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('\t') {
+            // the device classes at the end of the file start with `C `
+            in_vendor = line.starts_with(vendor) && line[vendor.len()..].starts_with("  ");
+            continue;
+        }
+        if !in_vendor || line.starts_with("\t\t") {
+            continue;
+        }
+        let Some((id, name)) = line[1..].split_once("  ") else {
+            continue;
+        };
+        if id == device {
+            return Some(name.trim().to_string());
+        }
+    }
+    None
+}
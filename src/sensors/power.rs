@@ -0,0 +1,111 @@
+use super::registry;
+use crate::config::config;
+use crate::utils::global;
+use colored::Colorize;
+use std::time::Instant;
+
+/*
+ ██████╗  ██████╗ ██╗    ██╗███████╗██████╗
+ ██╔══██╗██╔═══██╗██║    ██║██╔════╝██╔══██╗
+ ██████╔╝██║   ██║██║ █╗ ██║█████╗  ██████╔╝
+ ██╔═══╝ ██║   ██║██║███╗██║██╔══╝  ██╔══██╗
+ ██║     ╚██████╔╝╚███╔███╔╝███████╗██║  ██║
+ ╚═╝      ╚═════╝  ╚══╝╚══╝ ╚══════╝╚═╝  ╚═╝
+
+    Where the power comes from, as `ac.online` says. Every plug and unplug
+    runs $config.power.hook and the listeners (bar css classes), the shader
+    gets the time since the change (see `write_uniforms`).
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerSource {
+    Ac,
+    Battery,
+}
+
+impl PowerSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerSource::Ac => "ac",
+            PowerSource::Battery => "battery",
+        }
+    }
+}
+
+pub struct Power {
+    /// `None` without an AC adapter, desktops never change.
+    pub source: Option<PowerSource>,
+    pub changed_at: Option<Instant>,
+    listeners: Vec<Box<dyn Fn(PowerSource)>>,
+}
+
+global!(
+    power,
+    Power,
+    Power {
+        source: None,
+        changed_at: None,
+        listeners: vec![],
+    }
+);
+
+/// `listener` runs after every change, not for the source at startup.
+pub fn on_power_source_changed(listener: impl Fn(PowerSource) + 'static) {
+    power().listeners.push(Box::new(listener));
+}
+
+/// Runs after every sample. The first reading only sets the source, an adapter
+/// that disappears (and comes back) is not a change either.
+pub fn update_power_source() {
+    let source = registry()
+        .get("ac.online")
+        .filter(|entry| entry.available)
+        .and_then(|entry| entry.reading.values().first().copied())
+        .map(|online| match online > 0.0 {
+            true => PowerSource::Ac,
+            false => PowerSource::Battery,
+        });
+    let power = power();
+    if source == power.source {
+        return;
+    }
+    let previous = std::mem::replace(&mut power.source, source);
+    let (Some(_), Some(source)) = (previous, source) else {
+        return;
+    };
+    power.changed_at = Some(Instant::now());
+    eprintln!(
+        "[{}]: {}({})",
+        "power".green(),
+        "changed".yellow(),
+        source.name()
+    );
+    run_hook(source);
+    for listener in &power.listeners {
+        listener(source);
+    }
+}
+
+fn run_hook(source: PowerSource) {
+    let Some(hook) = &config().settings.power.hook else {
+        return;
+    };
+    let child = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .env("SHADERBAR_POWER_SOURCE", source.name())
+        .spawn();
+    match child {
+        // waited for on its own thread, so a slow hook does not stall the bar
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => eprintln!(
+            "[{}]: {}({}): {}",
+            "power".green(),
+            "hook".yellow(),
+            hook,
+            err.to_string().red()
+        ),
+    }
+}
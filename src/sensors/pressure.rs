@@ -0,0 +1,175 @@
+use super::{read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use std::time::{Duration, Instant};
+
+/*
+ ██████╗ ██████╗ ███████╗███████╗███████╗██╗   ██╗██████╗ ███████╗
+ ██╔══██╗██╔══██╗██╔════╝██╔════╝██╔════╝██║   ██║██╔══██╗██╔════╝
+ ██████╔╝██████╔╝█████╗  ███████╗███████╗██║   ██║██████╔╝█████╗
+ ██╔═══╝ ██╔══██╗██╔══╝  ╚════██║╚════██║██║   ██║██╔══██╗██╔══╝
+ ██║     ██║  ██║███████╗███████║███████║╚██████╔╝██║  ██║███████╗
+ ╚═╝     ╚═╝  ╚═╝╚══════╝╚══════╝╚══════╝ ╚═════╝ ╚═╝  ╚═╝╚══════╝
+
+    Pressure stall information from /proc/pressure/{cpu,memory,io}: the
+    share of time some (or all) tasks waited on the resource. `avg10` and
+    `avg60` are the kernel's running averages, `rate` is the `total` stall
+    time (µs) against the time between two samples. No PSI (before 4.20,
+    or `psi=0`) means no pressure sensors.
+
+    some avg10=0.12 avg60=0.05 avg300=0.01 total=1234567
+    full avg10=0.00 avg60=0.00 avg300=0.00 total=345678
+*/
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Resource {
+    Cpu,
+    Memory,
+    Io,
+}
+
+impl Resource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resource::Cpu => "cpu",
+            Resource::Memory => "memory",
+            Resource::Io => "io",
+        }
+    }
+}
+
+/// `some`: at least one task stalled, `full`: all of them (cpu has that since 5.13).
+#[derive(Clone, Copy, PartialEq)]
+pub enum Line {
+    Some,
+    Full,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stat {
+    Avg10,
+    Avg60,
+    Rate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stall {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    /// µs stalled since boot
+    pub total: u64,
+}
+
+/// The `some` or `full` line of a pressure file.
+pub fn parse_stall(contents: &str, line: Line) -> Option<Stall> {
+    let prefix = match line {
+        Line::Some => "some ",
+        Line::Full => "full ",
+    };
+    let fields = contents.lines().find_map(|x| x.strip_prefix(prefix))?;
+    let field = |key: &str| {
+        fields
+            .split_whitespace()
+            .find_map(|x| x.strip_prefix(key)?.strip_prefix('='))
+    };
+    Some(Stall {
+        avg10: field("avg10")?.parse().ok()?,
+        avg60: field("avg60")?.parse().ok()?,
+        avg300: field("avg300")?.parse().ok()?,
+        total: field("total")?.parse().ok()?,
+    })
+}
+
+pub struct Pressure {
+    resource: Resource,
+    line: Line,
+    stat: Stat,
+    name: String,
+    path: String,
+    /// the last `total` and when it was read, `Stat::Rate` only
+    last: Option<(u64, Instant)>,
+}
+
+impl Pressure {
+    pub fn new(resource: Resource, line: Line, stat: Stat) -> Self {
+        let line_name = match line {
+            Line::Some => "some",
+            Line::Full => "full",
+        };
+        let name = match stat {
+            Stat::Avg10 => format!("pressure.{}.{}", resource.name(), line_name),
+            Stat::Avg60 => format!("pressure.{}.{}.avg60", resource.name(), line_name),
+            Stat::Rate => format!("pressure.{}.{}.rate", resource.name(), line_name),
+        };
+        Pressure {
+            resource,
+            line,
+            stat,
+            name,
+            path: String::new(),
+            last: None,
+        }
+    }
+
+    /// Percent of the time since the last `total`, 0 for the first one.
+    pub fn rate(&mut self, total: u64, now: Instant) -> f64 {
+        let Some((last, at)) = self.last.replace((total, now)) else {
+            return 0.0;
+        };
+        let micros = now.duration_since(at).as_micros() as f64;
+        if micros <= 0.0 {
+            return 0.0;
+        }
+        // the averages are updated every 2 s, the counter right away
+        (100.0 * total.saturating_sub(last) as f64 / micros).min(100.0)
+    }
+}
+
+impl Sensor for Pressure {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        let path = detected.file(&format!("/proc/pressure/{}", self.resource.name()));
+        if path != self.path {
+            self.path = path;
+            self.last = None;
+        }
+        // psi=0 keeps the files, reading them fails
+        let Ok(contents) = read_string_from_file_sync(&self.path) else {
+            return false;
+        };
+        parse_stall(&contents, self.line).is_some()
+    }
+
+    fn sample(&mut self) -> Reading {
+        let Ok(contents) = read_string_from_file_sync(&self.path) else {
+            return Reading::Unavailable;
+        };
+        let Some(stall) = parse_stall(&contents, self.line) else {
+            return Reading::Unavailable;
+        };
+        match self.stat {
+            Stat::Avg10 => Reading::Value(stall.avg10),
+            Stat::Avg60 => Reading::Value(stall.avg60),
+            Stat::Rate => Reading::Value(self.rate(stall.total, Instant::now())),
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Percent
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 100.0)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn source(&self) -> String {
+        self.path.clone()
+    }
+}
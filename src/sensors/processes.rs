@@ -0,0 +1,276 @@
+use super::{Detected, Reading, Sensor};
+use crate::config::{config, SensorUnit};
+use crate::utils::global;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/*
+ ██████╗ ██████╗  ██████╗  ██████╗███████╗███████╗███████╗███████╗███████╗
+ ██╔══██╗██╔══██╗██╔═══██╗██╔════╝██╔════╝██╔════╝██╔════╝██╔════╝██╔════╝
+ ██████╔╝██████╔╝██║   ██║██║     █████╗  ███████╗███████╗█████╗  ███████╗
+ ██╔═══╝ ██╔══██╗██║   ██║██║     ██╔══╝  ╚════██║╚════██║██╔══╝  ╚════██║
+ ██║     ██║  ██║╚██████╔╝╚██████╗███████╗███████║███████║███████╗███████║
+ ╚═╝     ╚═╝  ╚═╝ ╚═════╝  ╚═════╝╚══════╝╚══════╝╚══════╝╚══════╝╚══════╝
+
+    Every /proc/[pid] every two seconds: cpu time from `stat`, resident
+    memory from `statm`. The top $config.processes.count by cpu and by
+    memory go to `top_processes()` (cmdline is read for those only), the
+    sensor value is the cpu of the top ones. The walk keeps its buffers,
+    a few hundred processes should not cost a few hundred allocations:
+    the paths, the samples and the two `Top`s, one filled by the walk and
+    one in `top_processes()`, swapped after each walk.
+*/
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub comm: String,
+    /// the arguments joined by spaces, empty for kernel threads
+    pub cmdline: String,
+    /// percent of one core since the last walk, like `top`
+    pub cpu: f64,
+    /// resident memory in bytes
+    pub rss: u64,
+}
+
+#[derive(Default)]
+pub struct Top {
+    pub by_cpu: Vec<Process>,
+    pub by_memory: Vec<Process>,
+}
+
+global!(top_processes, Top, Top::default());
+
+/// The fields of /proc/[pid]/stat that matter here.
+#[derive(Debug, PartialEq)]
+pub struct Stat<'a> {
+    pub comm: &'a str,
+    /// utime + stime in clock ticks
+    pub ticks: u64,
+    /// starttime, a pid can be reused by another process
+    pub start: u64,
+}
+
+/// `pid (comm) state ...`, comm can hold spaces and parentheses itself.
+pub fn parse_stat(contents: &str) -> Option<Stat<'_>> {
+    let open = contents.find('(')?;
+    let close = contents.rfind(')')?;
+    // counted from the state, utime is field 14 of the man page, stime 15, starttime 22
+    let mut fields = contents.get(close + 1..)?.split_whitespace().skip(11);
+    let utime = fields.next()?.parse::<u64>().ok()?;
+    let stime = fields.next()?.parse::<u64>().ok()?;
+    let start = fields.nth(6)?.parse::<u64>().ok()?;
+    Some(Stat {
+        comm: contents.get(open + 1..close)?,
+        ticks: utime + stime,
+        start,
+    })
+}
+
+#[derive(Default)]
+struct Sample {
+    pid: u32,
+    comm: String,
+    cpu: f64,
+    rss: u64,
+}
+
+pub struct TopProcesses {
+    proc_dir: PathBuf,
+    /// ticks, start time and the walk that last saw each pid
+    last: HashMap<u32, (u64, u64, u64)>,
+    walks: u64,
+    last_walk: Option<Instant>,
+    samples: Vec<Sample>,
+    top: Top,
+    buffer: String,
+    /// the pid as text, for `path`
+    pid: String,
+    path: PathBuf,
+    ticks_per_second: f64,
+    page_size: u64,
+}
+
+impl TopProcesses {
+    pub fn new() -> Self {
+        TopProcesses {
+            proc_dir: PathBuf::new(),
+            last: HashMap::new(),
+            walks: 0,
+            last_walk: None,
+            samples: vec![],
+            top: Top::default(),
+            buffer: String::new(),
+            pid: String::new(),
+            path: PathBuf::new(),
+            ticks_per_second: unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as f64,
+            page_size: unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64,
+        }
+    }
+
+    /// Reads `file` of `pid` into the buffer.
+    fn read(&mut self, pid: u32, file: &str) -> bool {
+        self.pid.clear();
+        write!(self.pid, "{}", pid).unwrap();
+        self.path.clear();
+        self.path.push(&self.proc_dir);
+        self.path.push(&self.pid);
+        self.path.push(file);
+        self.buffer.clear();
+        let file = std::fs::File::open(&self.path);
+        file.and_then(|mut file| file.read_to_string(&mut self.buffer))
+            .is_ok()
+    }
+
+    /// Walks the processes, the top `count` by cpu and by memory. Cpu is 0 on
+    /// the first walk, there is nothing to compare to.
+    pub fn walk(&mut self, now: Instant, count: usize) -> &Top {
+        let seconds = self
+            .last_walk
+            .replace(now)
+            .map(|at| now.duration_since(at).as_secs_f64());
+        self.walks += 1;
+        let Ok(dirs) = std::fs::read_dir(&self.proc_dir) else {
+            self.top.by_cpu.clear();
+            self.top.by_memory.clear();
+            return &self.top;
+        };
+        let mut used = 0;
+        for dir in dirs.filter_map(Result::ok) {
+            let Some(pid) = dir.file_name().to_str().and_then(|x| x.parse::<u32>().ok()) else {
+                continue;
+            };
+            // gone between read_dir and here
+            if !self.read(pid, "stat") {
+                continue;
+            }
+            let Some(stat) = parse_stat(&self.buffer) else {
+                continue;
+            };
+            let (ticks, start) = (stat.ticks, stat.start);
+            if used == self.samples.len() {
+                self.samples.push(Sample::default());
+            }
+            let sample = &mut self.samples[used];
+            sample.pid = pid;
+            sample.comm.clear();
+            sample.comm.push_str(stat.comm);
+            let last = self.last.insert(pid, (ticks, start, self.walks));
+            sample.cpu = match (seconds, last) {
+                (Some(seconds), _) if seconds <= 0.0 => 0.0,
+                // the same process as last time
+                (Some(seconds), Some((last, last_start, _))) if last_start == start => {
+                    ticks.saturating_sub(last) as f64 / self.ticks_per_second / seconds * 100.0
+                }
+                // started since the last walk, all of its time is new
+                (Some(seconds), _) => ticks as f64 / self.ticks_per_second / seconds * 100.0,
+                (None, _) => 0.0,
+            };
+            used += 1;
+            let resident = match self.read(pid, "statm") {
+                true => self.buffer.split_whitespace().nth(1),
+                false => None,
+            };
+            let pages = resident.and_then(|x| x.parse::<u64>().ok()).unwrap_or(0);
+            self.samples[used - 1].rss = pages * self.page_size;
+        }
+        self.samples.truncate(used);
+        let walks = self.walks;
+        self.last.retain(|_, (_, _, seen)| *seen == walks);
+
+        // taken out while `top` needs all of self
+        let mut by_cpu = std::mem::take(&mut self.top.by_cpu);
+        self.top(&mut by_cpu, count, |a, b| b.cpu.total_cmp(&a.cpu));
+        self.top.by_cpu = by_cpu;
+        let mut by_memory = std::mem::take(&mut self.top.by_memory);
+        self.top(&mut by_memory, count, |a, b| b.rss.cmp(&a.rss));
+        self.top.by_memory = by_memory;
+        &self.top
+    }
+
+    /// Fills `list` with the first `count` samples in `order`, reusing its processes.
+    fn top(
+        &mut self,
+        list: &mut Vec<Process>,
+        count: usize,
+        order: impl Fn(&Sample, &Sample) -> std::cmp::Ordering,
+    ) {
+        let count = count.min(self.samples.len());
+        list.truncate(count);
+        if count == 0 {
+            return;
+        }
+        // the order of the rest does not matter
+        self.samples.select_nth_unstable_by(count - 1, &order);
+        self.samples[..count].sort_by(&order);
+        list.resize_with(count, Process::default);
+        for (sample, process) in self.samples[..count].iter().zip(list.iter_mut()) {
+            process.pid = sample.pid;
+            process.comm.clear();
+            process.comm.push_str(&sample.comm);
+            process.cpu = sample.cpu;
+            process.rss = sample.rss;
+        }
+        for process in list.iter_mut() {
+            process.cmdline.clear();
+            if self.read(process.pid, "cmdline") {
+                let args = self.buffer.chars().map(|c| if c == '\0' { ' ' } else { c });
+                process.cmdline.extend(args);
+                let length = process.cmdline.trim_end().len();
+                process.cmdline.truncate(length);
+            }
+        }
+    }
+}
+
+impl Sensor for TopProcesses {
+    fn name(&self) -> &str {
+        "processes.cpu"
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        let proc_dir = PathBuf::from(detected.file("/proc"));
+        if proc_dir != self.proc_dir {
+            self.proc_dir = proc_dir;
+            self.last.clear();
+            self.last_walk = None;
+        }
+        let Ok(dirs) = std::fs::read_dir(&self.proc_dir) else {
+            return false;
+        };
+        let mut pids = dirs.filter_map(Result::ok);
+        pids.any(|dir| {
+            dir.file_name()
+                .to_str()
+                .is_some_and(|x| x.parse::<u32>().is_ok())
+        })
+    }
+
+    fn sample(&mut self) -> Reading {
+        let top = self.walk(Instant::now(), config().settings.processes.count);
+        let cpu = top.by_cpu.iter().map(|process| process.cpu).collect();
+        // the widget gets this walk, the next one fills what it showed so far
+        std::mem::swap(top_processes(), &mut self.top);
+        Reading::Values(cpu)
+    }
+
+    fn unit(&self) -> SensorUnit {
+        SensorUnit::Percent
+    }
+
+    /// Of one core, a busy multithreaded process goes past the top.
+    fn range(&self) -> (f64, f64) {
+        (0.0, 100.0)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn source(&self) -> String {
+        self.proc_dir.to_string_lossy().to_string()
+    }
+}
@@ -0,0 +1,233 @@
+use super::battery::{Battery, Kind};
+use super::cpufreq::{self, Policy};
+use super::drm::{Class, GpuEngines};
+use super::memory::{Memory, Zram, ZramKind};
+use super::pressure::{Line, Pressure, Resource, Stat};
+use super::processes::TopProcesses;
+use super::{ac, cpu, disk, memory, network, scale, sysfs, Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use crate::utils::{early_continue, global};
+use std::time::Instant;
+
+/*
+ ██████╗ ███████╗ ██████╗ ██╗███████╗████████╗██████╗ ██╗   ██╗
+ ██╔══██╗██╔════╝██╔════╝ ██║██╔════╝╚══██╔══╝██╔══██╗╚██╗ ██╔╝
+ ██████╔╝█████╗  ██║  ███╗██║███████╗   ██║   ██████╔╝ ╚████╔╝
+ ██╔══██╗██╔══╝  ██║   ██║██║╚════██║   ██║   ██╔══██╗  ╚██╔╝
+ ██║  ██║███████╗╚██████╔╝██║███████║   ██║   ██║  ██║   ██║
+ ╚═╝  ╚═╝╚══════╝ ╚═════╝ ╚═╝╚══════╝   ╚═╝   ╚═╝  ╚═╝   ╚═╝
+
+    Sensors are kept in registration order, lookups are by name.
+    Unknown and unavailable sensors look the same from the outside.
+*/
+
+global!(registry, Registry, Registry::builtin());
+
+pub struct Entry {
+    pub sensor: Box<dyn Sensor>,
+    pub available: bool,
+    pub reading: Reading,
+    /// `reading` mapped to 0..255, see `scale.rs`
+    pub scaled: Vec<u8>,
+    next_sample: Instant,
+}
+
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+impl Registry {
+    pub fn builtin() -> Registry {
+        let mut registry = Registry { entries: vec![] };
+        registry.register(Box::new(cpu::CpuLoad::new()));
+        registry.register(Box::new(cpu::CpuUsage::new(cpu::Kind::Usage)));
+        registry.register(Box::new(cpu::CpuUsage::new(cpu::Kind::User)));
+        registry.register(Box::new(cpu::CpuUsage::new(cpu::Kind::System)));
+        registry.register(Box::new(cpu::CpuUsage::new(cpu::Kind::Iowait)));
+        registry.register(Box::new(cpu::CpuUsage::new(cpu::Kind::Steal)));
+        registry.register(Box::new(cpufreq::CpuFreq::new()));
+        registry.register(Box::new(cpufreq::CpuPolicy::new(Policy::Governor)));
+        registry.register(Box::new(cpufreq::CpuPolicy::new(Policy::Preference)));
+        registry.register(Box::new(cpufreq::CpuThrottle::new()));
+        registry.register(Box::new(sysfs::SysfsSensor::temp("cpu.temp")));
+        registry.register(Box::new(sysfs::SysfsSensor::temp("gpu.temp")));
+        registry.register(Box::new(sysfs::SysfsSensor::fan("cpu.fan")));
+        registry.register(Box::new(sysfs::SysfsSensor::fan("gpu.fan")));
+        registry.register(Box::new(sysfs::SysfsSensor::gpu_usage()));
+        for class in [Class::Busy, Class::Render, Class::Video, Class::Compute] {
+            registry.register(Box::new(GpuEngines::new(class)));
+        }
+        registry.register(Box::new(Battery::new(Kind::Capacity)));
+        registry.register(Box::new(Battery::new(Kind::Charging)));
+        registry.register(Box::new(Battery::new(Kind::Power)));
+        registry.register(Box::new(Battery::new(Kind::Rate)));
+        registry.register(Box::new(Battery::new(Kind::TimeToEmpty)));
+        registry.register(Box::new(Battery::new(Kind::TimeToFull)));
+        registry.register(Box::new(Battery::new(Kind::Health)));
+        registry.register(Box::new(ac::AcOnline::new()));
+        registry.register(Box::new(Memory::new(memory::Kind::Used)));
+        registry.register(Box::new(Memory::new(memory::Kind::Breakdown)));
+        registry.register(Box::new(Memory::new(memory::Kind::Swap)));
+        registry.register(Box::new(Memory::new(memory::Kind::Session)));
+        registry.register(Box::new(Zram::new(ZramKind::Used)));
+        registry.register(Box::new(Zram::new(ZramKind::Ratio)));
+        registry.register(Box::new(network::NetworkLoad::rx()));
+        registry.register(Box::new(network::NetworkLoad::tx()));
+        registry.register(Box::new(disk::DiskUsage::new()));
+        registry.register(Box::new(disk::DiskIo::new(disk::Kind::Read)));
+        registry.register(Box::new(disk::DiskIo::new(disk::Kind::Write)));
+        registry.register(Box::new(disk::DiskIo::new(disk::Kind::Busy)));
+        registry.register(Box::new(TopProcesses::new()));
+        for resource in [Resource::Cpu, Resource::Memory, Resource::Io] {
+            for line in [Line::Some, Line::Full] {
+                for stat in [Stat::Avg10, Stat::Avg60, Stat::Rate] {
+                    registry.register(Box::new(Pressure::new(resource, line, stat)));
+                }
+            }
+        }
+        registry
+    }
+
+    /// A sensor with the name of an existing one replaces it.
+    pub fn register(&mut self, sensor: Box<dyn Sensor>) {
+        self.entries
+            .retain(|entry| entry.sensor.name() != sensor.name());
+        self.entries.push(Entry {
+            sensor,
+            available: false,
+            reading: Reading::Unavailable,
+            scaled: vec![],
+            next_sample: Instant::now(),
+        });
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.sensor.name() == name)
+    }
+
+    /// The first scaled value, `None` while unavailable.
+    pub fn value(&self, name: &str) -> Option<u8> {
+        self.values(name).first().copied()
+    }
+
+    /// All scaled values, empty while unavailable.
+    pub fn values(&self, name: &str) -> &[u8] {
+        match self.get(name) {
+            Some(entry) => &entry.scaled,
+            None => &[],
+        }
+    }
+
+    pub fn available(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.available)
+            .map(|entry| entry.sensor.name().to_string())
+            .collect()
+    }
+
+    /// Disabled sensors are not missing, they are not wanted.
+    pub fn is_incomplete(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| !entry.available && scale::is_enabled(entry.sensor.name()))
+    }
+
+    /// True if the set of available sensors changed.
+    pub fn detect(&mut self, detected: &Detected) -> bool {
+        self.detect_entries(detected, true)
+    }
+
+    /// Like `detect`, but sensors that work are left alone, their deltas with them.
+    pub fn detect_missing(&mut self, detected: &Detected) -> bool {
+        self.detect_entries(detected, false)
+    }
+
+    fn detect_entries(&mut self, detected: &Detected, all: bool) -> bool {
+        let before = self.available();
+        for entry in &mut self.entries {
+            early_continue!(!all && entry.available);
+            let enabled = scale::is_enabled(entry.sensor.name());
+            entry.available = enabled && entry.sensor.detect(detected);
+            entry.next_sample = Instant::now();
+            if !entry.available {
+                entry.reading = Reading::Unavailable;
+                entry.scaled.clear();
+            }
+        }
+        self.available() != before
+    }
+
+    pub fn sample(&mut self, now: Instant) {
+        for entry in &mut self.entries {
+            early_continue!(!entry.available || entry.next_sample > now);
+            entry.next_sample = now + entry.sensor.poll_interval();
+            entry.reading = entry.sensor.sample();
+            if entry.reading == Reading::Unavailable {
+                // until the next detection finds it again
+                entry.available = false;
+            }
+            let scale = scale::scale(entry.sensor.as_ref());
+            entry.scaled = entry
+                .reading
+                .values()
+                .iter()
+                .map(|value| scale.apply(*value))
+                .collect();
+        }
+    }
+}
+
+impl Entry {
+    /// The mean of the reading in the configured unit, like `52°C`.
+    pub fn display(&self) -> Option<String> {
+        if !self.available {
+            return None;
+        }
+        if let Some(text) = self.sensor.text() {
+            return Some(text);
+        }
+        let values = self.reading.values();
+        if values.is_empty() {
+            return None;
+        }
+        let scale = scale::scale(self.sensor.as_ref());
+        let mean = values.iter().map(|v| scale.convert(*v)).sum::<f64>() / values.len() as f64;
+        Some(match scale.unit {
+            SensorUnit::Celsius => format!("{:.0}°C", mean),
+            SensorUnit::Fahrenheit => format!("{:.0}°F", mean),
+            SensorUnit::Rpm => format!("{:.0} rpm", mean),
+            SensorUnit::Percent => format!("{:.0}%", mean),
+            SensorUnit::Watts => format!("{:.1} W", mean),
+            SensorUnit::Mhz => format!("{:.0} MHz", mean),
+            SensorUnit::Seconds => {
+                let minutes = mean.max(0.0) as u64 / 60;
+                format!("{}:{:02}", minutes / 60, minutes % 60)
+            }
+            // what all interfaces move together
+            SensorUnit::Bytes => bytes_per_second(mean * values.len() as f64),
+            SensorUnit::Raw => format!("{}", mean),
+        })
+    }
+}
+
+/// `512 B/s`, `1.4 MiB/s`
+fn bytes_per_second(bytes: f64) -> String {
+    let units = ["B/s", "KiB/s", "MiB/s", "GiB/s"];
+    let mut value = bytes.max(0.0);
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{:.0} {}", value, units[unit]),
+        _ => format!("{:.1} {}", value, units[unit]),
+    }
+}
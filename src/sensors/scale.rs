@@ -0,0 +1,92 @@
+use super::Sensor;
+use crate::config::{config, SensorCurve, SensorSettings, SensorUnit};
+
+/*
+ ███████╗ ██████╗ █████╗ ██╗     ███████╗
+ ██╔════╝██╔════╝██╔══██╗██║     ██╔════╝
+ ███████╗██║     ███████║██║     █████╗
+ ╚════██║██║     ██╔══██║██║     ██╔══╝
+ ███████║╚██████╗██║  ██║███████╗███████╗
+ ╚══════╝ ╚═════╝╚═╝  ╚═╝╚══════╝╚══════╝
+
+    Turns a sensor reading into the 0..255 the shader gets.
+    Unit and range come from the sensor (`Sensor::unit`, `Sensor::range`),
+    every part can be overridden in $config.sensors.<name>.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scale {
+    /// The unit the sensor reports in.
+    pub from: SensorUnit,
+    /// The unit `min` and `max` are in.
+    pub unit: SensorUnit,
+    pub min: f64,
+    pub max: f64,
+    pub curve: SensorCurve,
+}
+
+pub fn scale(sensor: &dyn Sensor) -> Scale {
+    let (min, max) = sensor.range();
+    let scale = Scale {
+        from: sensor.unit(),
+        unit: sensor.unit(),
+        min,
+        max,
+        curve: SensorCurve::Linear,
+    };
+    match config().settings.sensors.get(sensor.name()) {
+        Some(settings) => scale.with(settings),
+        None => scale,
+    }
+}
+
+/// `$config.sensors.<name>.enabled`, sensors without a section are enabled.
+pub fn is_enabled(name: &str) -> bool {
+    match config().settings.sensors.get(name) {
+        Some(settings) => settings.enabled,
+        None => true,
+    }
+}
+
+/// Only temperatures can be converted, `raw` leaves every value as it is.
+fn convert(from: SensorUnit, to: SensorUnit, value: f64) -> f64 {
+    match (from, to) {
+        (SensorUnit::Celsius, SensorUnit::Fahrenheit) => value * 1.8 + 32.0,
+        (SensorUnit::Fahrenheit, SensorUnit::Celsius) => (value - 32.0) / 1.8,
+        _ => value,
+    }
+}
+
+impl Scale {
+    fn with(mut self, settings: &SensorSettings) -> Scale {
+        // the default range has to follow a new unit
+        if let Some(unit) = settings.unit {
+            self.min = convert(self.unit, unit, self.min);
+            self.max = convert(self.unit, unit, self.max);
+            self.unit = unit;
+        }
+        self.min = settings.min.unwrap_or(self.min);
+        self.max = settings.max.unwrap_or(self.max);
+        self.curve = settings.curve;
+        self
+    }
+
+    /// A reading in the unit of `min` and `max`.
+    pub fn convert(&self, value: f64) -> f64 {
+        convert(self.from, self.unit, value)
+    }
+
+    /// Clamped to min..max, so a 7000 RPM fan on a 5000 RPM scale reads full
+    /// instead of wrapping around.
+    pub fn apply(&self, value: f64) -> u8 {
+        let value = self.convert(value);
+        let x = ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0);
+        let x = match self.curve {
+            SensorCurve::Linear => x,
+            SensorCurve::Sqrt => x.sqrt(),
+            SensorCurve::Square => x * x,
+            SensorCurve::Log => (1.0 + 9.0 * x).log10(),
+        };
+        (x * 255.0).round() as u8
+    }
+}
@@ -0,0 +1,104 @@
+use super::{read_string_from_file_sync, Detected, Reading, Sensor};
+use crate::config::SensorUnit;
+use std::time::Duration;
+
+/*
+ ███████╗██╗   ██╗███████╗███████╗███████╗
+ ██╔════╝╚██╗ ██╔╝██╔════╝██╔════╝██╔════╝
+ ███████╗ ╚████╔╝ ███████╗█████╗  ███████╗
+ ╚════██║  ╚██╔╝  ╚════██║██╔══╝  ╚════██║
+ ███████║   ██║   ███████║██║     ███████║
+ ╚══════╝   ╚═╝   ╚══════╝╚═╝     ╚══════╝
+
+    Everything that is a single value in a single file.
+*/
+
+pub struct SysfsSensor {
+    name: &'static str,
+    /// what `detect_sensors` calls the file
+    key: &'static str,
+    unit: SensorUnit,
+    range: (f64, f64),
+    poll_interval: Duration,
+    parse: fn(&str) -> Option<f64>,
+    path: String,
+}
+
+impl SysfsSensor {
+    /// hwmon reports millidegrees
+    pub fn temp(name: &'static str) -> Self {
+        SysfsSensor {
+            name,
+            key: name,
+            unit: SensorUnit::Celsius,
+            range: (0.0, 100.0),
+            poll_interval: Duration::from_secs(1),
+            parse: |text| Some(text.parse::<f64>().ok()? / 1000.0),
+            path: String::new(),
+        }
+    }
+
+    pub fn fan(name: &'static str) -> Self {
+        SysfsSensor {
+            name,
+            key: name,
+            unit: SensorUnit::Rpm,
+            range: (0.0, 5000.0),
+            poll_interval: Duration::from_secs(1),
+            parse: |text| text.parse().ok(),
+            path: String::new(),
+        }
+    }
+
+    pub fn gpu_usage() -> Self {
+        SysfsSensor {
+            name: "gpu.usage",
+            key: "gpu[0].usage",
+            unit: SensorUnit::Percent,
+            range: (0.0, 100.0),
+            poll_interval: Duration::from_secs(1),
+            parse: |text| text.parse().ok(),
+            path: String::new(),
+        }
+    }
+
+    fn read(&self) -> Option<f64> {
+        let text = read_string_from_file_sync(&self.path).ok()?;
+        (self.parse)(&text)
+    }
+}
+
+impl Sensor for SysfsSensor {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn detect(&mut self, detected: &Detected) -> bool {
+        self.path = detected.path(self.name, self.key).unwrap_or_default();
+        // hwmon fan paths are guessed from the temp paths and may not exist
+        !self.path.is_empty() && self.read().is_some()
+    }
+
+    fn sample(&mut self) -> Reading {
+        match self.read() {
+            Some(value) => Reading::Value(value),
+            None => Reading::Unavailable,
+        }
+    }
+
+    fn unit(&self) -> SensorUnit {
+        self.unit
+    }
+
+    fn range(&self) -> (f64, f64) {
+        self.range
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    fn source(&self) -> String {
+        self.path.clone()
+    }
+}
@@ -0,0 +1,764 @@
+use super::ac::AcOnline;
+use super::battery::{read_totals, Battery, Kind};
+use super::cpu::{self, parse_times, CpuLoad, CpuTimes, CpuUsage};
+use super::cpufreq::{CpuFreq, CpuPolicy, CpuThrottle, Policy};
+use super::disk::{self, DiskIo, DiskUsage};
+use super::drm::{self, parse_fdinfo, read_clients, Class, DrmUsage, GpuEngines, GpuLoad};
+use super::interfaces::{graphed, scan, Interface, InterfaceKind};
+use super::memory::{self, read_mm_stat, Memory, Zram, ZramKind};
+use super::network::{counter_delta, NetworkLoad, Peak};
+use super::pressure::{parse_stall, Line, Pressure, Resource, Stall, Stat};
+use super::processes::{self, parse_stat, TopProcesses};
+use super::sysfs::SysfsSensor;
+use super::{Detected, Reading, Sensor};
+use crate::config::{DiskSettings, NetworkScale, NetworkSettings};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/*
+ ████████╗███████╗███████╗████████╗███████╗
+ ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝██╔════╝
+    ██║   █████╗  ███████╗   ██║   ███████╗
+    ██║   ██╔══╝  ╚════██║   ██║   ╚════██║
+    ██║   ███████╗███████║   ██║   ███████║
+    ╚═╝   ╚══════╝╚══════╝   ╚═╝   ╚══════╝
+
+    Detection and parsing against the trees in tests/fixtures.
+*/
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// What detection found as `key`, relative to the fixture root.
+fn found(detected: &Detected, root: &Path, key: &str) -> Option<String> {
+    let value = detected.get(key)?.to_string();
+    let root = root.to_str().unwrap();
+    Some(value.strip_prefix(root).unwrap_or(&value).to_string())
+}
+
+fn sample(sensor: &mut dyn Sensor, detected: &Detected) -> Reading {
+    match sensor.detect(detected) {
+        true => sensor.sample(),
+        false => Reading::Unavailable,
+    }
+}
+
+fn assert_close(reading: Reading, expected: &[f64]) {
+    let values = reading.values();
+    assert_eq!(values.len(), expected.len(), "{:?}", values);
+    for (value, expected) in values.iter().zip(expected) {
+        assert!((value - expected).abs() < 0.01, "{} != {}", value, expected);
+    }
+}
+
+#[tokio::test]
+async fn intel_laptop_detection() {
+    let root = fixture("intel-laptop");
+    let detected = Detected::detect_in(&root).await;
+    let found = |key| found(&detected, &root, key);
+    assert_eq!(found("cpu.count").as_deref(), Some("8"));
+    // coretemp labels do not say "cpu", the hwmon name does
+    assert_eq!(
+        found("cpu.temp").as_deref(),
+        Some("/sys/class/hwmon/hwmon3/temp1_input")
+    );
+    assert_eq!(
+        found("cpu.fan").as_deref(),
+        Some("/sys/class/hwmon/hwmon3/fan1_input")
+    );
+    assert_eq!(found("gpu.temp"), None);
+    assert_eq!(found("gpu.count").as_deref(), Some("0"));
+    // the mouse battery is not one of ours
+    assert_eq!(found("battery.count").as_deref(), Some("2"));
+    assert_eq!(
+        found("battery[0].path").as_deref(),
+        Some("/sys/class/power_supply/BAT0")
+    );
+    assert_eq!(
+        found("battery[1].path").as_deref(),
+        Some("/sys/class/power_supply/BAT1")
+    );
+}
+
+#[tokio::test]
+async fn intel_laptop_readings() {
+    let detected = Detected::detect_in(&fixture("intel-laptop")).await;
+    let mut cpu_temp = SysfsSensor::temp("cpu.temp");
+    assert_close(sample(&mut cpu_temp, &detected), &[52.0]);
+    // the fan is guessed next to coretemp, but the thinkpad hwmon has it
+    let mut cpu_fan = SysfsSensor::fan("cpu.fan");
+    assert!(!cpu_fan.detect(&detected));
+}
+
+#[tokio::test]
+async fn two_batteries_add_up() {
+    let detected = Detected::detect_in(&fixture("intel-laptop")).await;
+    let battery = |kind| {
+        let mut battery = Battery::new(kind);
+        sample(&mut battery, &detected)
+    };
+    // (45.12 + 20.1) / (51.86 + 23.2) Wh
+    assert_close(battery(Kind::Capacity), &[86.89]);
+    assert_close(battery(Kind::Charging), &[0.0]);
+    assert_close(battery(Kind::Power), &[7.81]);
+    assert_close(battery(Kind::Rate), &[-7.81]);
+    assert_close(battery(Kind::TimeToEmpty), &[30051.45]);
+    assert_close(battery(Kind::TimeToFull), &[]);
+    // (51.86 + 23.2) / (57.02 + 23.48) Wh
+    assert_close(battery(Kind::Health), &[93.24]);
+}
+
+#[tokio::test]
+async fn ac_adapter() {
+    let root = fixture("intel-laptop");
+    let detected = Detected::detect_in(&root).await;
+    assert_eq!(
+        found(&detected, &root, "ac[0].path").as_deref(),
+        Some("/sys/class/power_supply/AC")
+    );
+    assert_close(sample(&mut AcOnline::new(), &detected), &[0.0]);
+    let detected = Detected::detect_in(&fixture("amd-desktop")).await;
+    assert!(!AcOnline::new().detect(&detected));
+}
+
+#[test]
+fn charge_based_battery() {
+    let dir = std::env::temp_dir().join(format!("shaderbar-battery-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |file: &str, value: &str| std::fs::write(dir.join(file), value).unwrap();
+    write("status", "Charging");
+    write("charge_now", "2500000");
+    write("charge_full", "5000000");
+    write("current_now", "1000000");
+    write("voltage_now", "12000000");
+    write("voltage_min_design", "11400000");
+    let totals = read_totals(std::slice::from_ref(&dir)).unwrap();
+    // µAh times the design voltage, the power from the current voltage
+    assert_close(
+        Reading::Values(vec![totals.energy_now, totals.energy_full, totals.power]),
+        &[28.5, 57.0, 12.0],
+    );
+    let mut time_to_full = Battery::new(Kind::TimeToFull);
+    // a configured path limits the sensor to that battery
+    let configured = [("battery.time_to_full", dir.join("status"))];
+    let detected = Detected {
+        root: PathBuf::from("/"),
+        found: HashMap::new(),
+        configured: configured
+            .iter()
+            .map(|(name, path)| (name.to_string(), path.to_string_lossy().to_string()))
+            .collect(),
+        disk: DiskSettings::default(),
+    };
+    assert!(time_to_full.detect(&detected));
+    assert_close(time_to_full.sample(), &[8550.0]);
+    // a jump in power only moves the estimate a bit
+    write("current_now", "2000000");
+    assert_close(time_to_full.sample(), &[28.5 / 13.2 * 3600.0]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn amd_desktop_detection() {
+    let root = fixture("amd-desktop");
+    let detected = Detected::detect_in(&root).await;
+    let found = |key| found(&detected, &root, key);
+    assert_eq!(found("cpu.count").as_deref(), Some("12"));
+    assert_eq!(
+        found("cpu.temp").as_deref(),
+        Some("/sys/class/hwmon/hwmon1/temp1_input")
+    );
+    assert_eq!(
+        found("gpu.temp").as_deref(),
+        Some("/sys/class/hwmon/hwmon2/temp1_input")
+    );
+    assert_eq!(
+        found("gpu.fan").as_deref(),
+        Some("/sys/class/hwmon/hwmon2/fan1_input")
+    );
+    assert_eq!(found("gpu.count").as_deref(), Some("1"));
+    assert_eq!(
+        found("gpu[0].usage").as_deref(),
+        Some("/sys/class/drm/card0/device/gpu_busy_percent")
+    );
+    assert_eq!(
+        found("gpu[0].model").as_deref(),
+        Some("Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]")
+    );
+    assert_eq!(found("battery.count").as_deref(), Some("0"));
+}
+
+#[tokio::test]
+async fn amd_desktop_readings() {
+    let detected = Detected::detect_in(&fixture("amd-desktop")).await;
+    let mut cpu_temp = SysfsSensor::temp("cpu.temp");
+    assert_close(sample(&mut cpu_temp, &detected), &[61.25]);
+    let mut gpu_temp = SysfsSensor::temp("gpu.temp");
+    assert_close(sample(&mut gpu_temp, &detected), &[48.0]);
+    let mut gpu_fan = SysfsSensor::fan("gpu.fan");
+    assert_close(sample(&mut gpu_fan, &detected), &[1100.0]);
+    let mut gpu_usage = SysfsSensor::gpu_usage();
+    assert_close(sample(&mut gpu_usage, &detected), &[14.0]);
+    assert!(!Battery::new(Kind::Capacity).detect(&detected));
+}
+
+#[tokio::test]
+async fn vm_without_hwmon() {
+    let root = fixture("vm");
+    let detected = Detected::detect_in(&root).await;
+    let mut keys = detected.found().keys().cloned().collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(
+        keys,
+        [
+            "ac.count",
+            "battery.count",
+            "cpu.count",
+            "disk.count",
+            "disk[0].name",
+            "drm.count",
+            "gpu.count",
+            "zram.count"
+        ]
+    );
+    assert_eq!(found(&detected, &root, "cpu.count").as_deref(), Some("2"));
+    for name in ["cpu.temp", "gpu.temp"] {
+        assert!(!SysfsSensor::temp(name).detect(&detected));
+    }
+    for name in ["cpu.fan", "gpu.fan"] {
+        assert!(!SysfsSensor::fan(name).detect(&detected));
+    }
+    assert!(!SysfsSensor::gpu_usage().detect(&detected));
+    assert!(NetworkLoad::rx().detect(&detected));
+}
+
+fn names(interfaces: &[&Interface]) -> Vec<String> {
+    interfaces.iter().map(|i| i.name.clone()).collect()
+}
+
+#[test]
+fn intel_laptop_interfaces() {
+    let interfaces = scan(&fixture("intel-laptop"));
+    let kinds = interfaces
+        .iter()
+        .map(|i| (i.name.as_str(), i.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            ("docker0", InterfaceKind::Bridge),
+            ("lo", InterfaceKind::Loopback),
+            ("wg0", InterfaceKind::Tunnel),
+            ("wlp0s20f3", InterfaceKind::Wifi)
+        ]
+    );
+    let wifi = &interfaces[3];
+    assert_eq!(wifi.operstate, "up");
+    assert_eq!(
+        wifi.ipv4,
+        ["192.168.1.42".parse::<std::net::Ipv4Addr>().unwrap()]
+    );
+    let ipv6 = wifi.ipv6.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    assert_eq!(ipv6, ["2001:db8::1:42", "fe80::a1b2:c3ff:fed4:e5f6"]);
+    assert_eq!(wifi.speed, None);
+    // routes only cover the main table, loopback addresses have none
+    assert!(interfaces[1].ipv4.is_empty());
+    assert_eq!(interfaces[2].ipv4[0].to_string(), "10.8.0.2");
+    assert_eq!(interfaces[0].ipv4[0].to_string(), "172.17.0.1");
+
+    let mut settings = NetworkSettings::default();
+    assert_eq!(names(&graphed(&interfaces, &settings)), ["wlp0s20f3"]);
+    // docker0 is down, wg0 says unknown like every tunnel
+    settings.graph = vec!["tunnel".to_string(), "docker0".to_string()];
+    assert_eq!(names(&graphed(&interfaces, &settings)), ["wg0"]);
+    settings.ignore = vec!["wg0".to_string()];
+    assert!(graphed(&interfaces, &settings).is_empty());
+}
+
+#[test]
+fn link_speed() {
+    let desktop = scan(&fixture("amd-desktop"));
+    let enp5s0 = desktop.iter().find(|i| i.name == "enp5s0").unwrap();
+    assert_eq!(enp5s0.kind, InterfaceKind::Ethernet);
+    assert_eq!(enp5s0.speed, Some(1000));
+    assert_eq!(enp5s0.ipv4[0].to_string(), "192.168.178.20");
+    let virbr0 = desktop.iter().find(|i| i.name == "virbr0").unwrap();
+    assert_eq!(virbr0.kind, InterfaceKind::Bridge);
+    // virtio says -1
+    let vm = scan(&fixture("vm"));
+    let enp1s0 = vm.iter().find(|i| i.name == "enp1s0").unwrap();
+    assert_eq!(enp1s0.speed, None);
+    assert_eq!(enp1s0.ipv4[0].to_string(), "10.0.2.15");
+}
+
+#[test]
+fn counter_wrap_and_reset() {
+    assert_eq!(counter_delta(100, 150), Some(50));
+    assert_eq!(counter_delta(u32::MAX as u64 - 9, 10), Some(20));
+    // a small counter running backwards was reset, not wrapped
+    assert_eq!(counter_delta(5000, 10), None);
+    assert_eq!(counter_delta(1 << 40, 5), None);
+}
+
+#[test]
+fn network_rates() {
+    let interfaces = scan(&fixture("intel-laptop"));
+    let settings = NetworkSettings::default();
+    let wifi = graphed(&interfaces, &settings);
+    let dev = |bytes: u64| format!("wlp0s20f3: {} 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n", bytes);
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+    let mut rx = NetworkLoad::rx();
+    assert_eq!(rx.rates(&dev(4_000_000), &wifi, at(0), &settings), [0.0]);
+    let rates = rx.rates(&dev(4_500_000), &wifi, at(500), &settings);
+    assert_close(Reading::Values(rates), &[1_000_000.0]);
+    assert_eq!(rx.range(), (0.0, 1_000_000.0));
+    // the driver reset its counters
+    assert_eq!(rx.rates(&dev(300), &wifi, at(1000), &settings), [0.0]);
+    let rates = rx.rates(&dev(2300), &wifi, at(2000), &settings);
+    assert_close(Reading::Values(rates), &[2000.0]);
+
+    let link = NetworkSettings {
+        scale: NetworkScale::Link,
+        ..NetworkSettings::default()
+    };
+    let desktop = scan(&fixture("amd-desktop"));
+    let mut tx = NetworkLoad::tx();
+    tx.rates("", &graphed(&desktop, &link), at(0), &link);
+    // 1000 Mbit/s
+    assert_eq!(tx.range(), (0.0, 125_000_000.0));
+    // wifi has no speed and falls back to the decaying peak
+    tx.rates("", &wifi, at(0), &link);
+    assert_eq!(tx.range(), (0.0, link.min_peak));
+}
+
+#[test]
+fn decaying_and_windowed_peak() {
+    let start = Instant::now();
+    let at = |s: u64| start + Duration::from_secs(s);
+    let decay = NetworkScale::Decay;
+    let mut peak = Peak::new();
+    assert_eq!(peak.update(1000.0, at(0), decay, 10.0), 1000.0);
+    assert_eq!(peak.update(0.0, at(10), decay, 10.0), 500.0);
+    assert_eq!(peak.update(800.0, at(10), decay, 10.0), 800.0);
+
+    let window = NetworkScale::Window;
+    let mut peak = Peak::new();
+    assert_eq!(peak.update(1000.0, at(0), window, 10.0), 1000.0);
+    assert_eq!(peak.update(200.0, at(5), window, 10.0), 1000.0);
+    assert_eq!(peak.update(100.0, at(11), window, 10.0), 200.0);
+    assert_eq!(peak.update(50.0, at(16), window, 10.0), 100.0);
+}
+
+#[tokio::test]
+async fn disks_without_loop_and_dm() {
+    let laptop = Detected::detect_in(&fixture("intel-laptop")).await;
+    let found_in = |detected: &Detected, key| detected.get(key).map(|x| x.to_string());
+    assert_eq!(found_in(&laptop, "disk.count").as_deref(), Some("1"));
+    assert_eq!(
+        found_in(&laptop, "disk[0].name").as_deref(),
+        Some("nvme0n1")
+    );
+    // the card reader is empty, dm-0 sits on top of nvme0n1
+    let desktop = Detected::detect_in(&fixture("amd-desktop")).await;
+    assert_eq!(found_in(&desktop, "disk.count").as_deref(), Some("2"));
+    assert_eq!(
+        found_in(&desktop, "disk[0].name").as_deref(),
+        Some("nvme0n1")
+    );
+    assert_eq!(found_in(&desktop, "disk[1].name").as_deref(), Some("sda"));
+
+    let mut usage = DiskUsage::new();
+    assert!(usage.detect(&desktop));
+    let values = usage.sample().values();
+    assert_eq!(values.len(), 1);
+    assert!((0.0..=100.0).contains(&values[0]));
+}
+
+#[tokio::test]
+async fn disk_throughput_and_busy() {
+    let root = fixture("amd-desktop");
+    let mut detected = Detected::detect_in(&root).await;
+    let stats = std::fs::read_to_string(root.join("proc/diskstats")).unwrap();
+    // 2048 sectors read and 500 ms busy later
+    let later = stats
+        .replace(" 35420886 ", " 35422934 ")
+        .replace(" 512844 ", " 513344 ");
+    let start = Instant::now();
+    let settings = DiskSettings::default();
+    let mut read = DiskIo::new(disk::Kind::Read);
+    assert!(read.detect(&detected));
+    assert_eq!(read.rates(&stats, start, &settings), [0.0, 0.0]);
+    let second = start + Duration::from_secs(1);
+    assert_eq!(read.rates(&later, second, &settings), [1048576.0, 0.0]);
+    assert_eq!(read.range(), (0.0, 1048576.0));
+    let mut busy = DiskIo::new(disk::Kind::Busy);
+    assert!(busy.detect(&detected));
+    busy.rates(&stats, start, &settings);
+    assert_eq!(busy.rates(&later, second, &settings), [50.0, 0.0]);
+
+    // configured devices that are not there are left out
+    detected.disk.devices = vec!["sda".to_string(), "sdz".to_string()];
+    let mut write = DiskIo::new(disk::Kind::Write);
+    assert!(write.detect(&detected));
+    assert_eq!(
+        write.source(),
+        format!("{}/proc/diskstats (sda)", root.display())
+    );
+}
+
+#[tokio::test]
+async fn cpu_load_since_boot() {
+    let detected = Detected::detect_in(&fixture("vm")).await;
+    let mut cpu_load = CpuLoad::new();
+    // busy / total of each cpuN line, the first sample has nothing to subtract
+    assert_close(sample(&mut cpu_load, &detected), &[15.16, 15.18]);
+    // the same counters again, nothing happened in between
+    assert_close(cpu_load.sample(), &[0.0, 0.0]);
+}
+
+#[tokio::test]
+async fn cpu_usage_split() {
+    let detected = Detected::detect_in(&fixture("vm")).await;
+    // out of the first eight columns of the `cpu` line, since boot
+    let expected = [
+        (cpu::Kind::Usage, 15.17),
+        (cpu::Kind::User, 11.33),
+        (cpu::Kind::System, 3.85),
+        (cpu::Kind::Iowait, 0.19),
+        (cpu::Kind::Steal, 0.0),
+    ];
+    for (kind, expected) in expected {
+        let mut usage = CpuUsage::new(kind);
+        assert_close(sample(&mut usage, &detected), &[expected]);
+    }
+    let mut steal = CpuUsage::new(cpu::Kind::Steal);
+    steal.usage(parse_times("cpu  100 0 100 700 0 0 0 0 0 0").unwrap());
+    // 20 of 100 ticks went to the hypervisor
+    let next = steal.usage(parse_times("cpu  130 0 120 730 0 0 0 20 10 0").unwrap());
+    assert_close(Reading::Value(next), &[20.0]);
+    assert_eq!(parse_times("intr 52718334 9 0"), None);
+}
+
+#[test]
+fn cpu_times_read_once_per_tick() {
+    let path = std::env::temp_dir().join(format!("shaderbar-stat-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+    let first = [100, 0, 100, 700, 0, 0, 0, 0];
+    let second = [130, 0, 120, 730, 0, 0, 0, 20];
+    let mut times = CpuTimes::default();
+    std::fs::write(path, "cpu  100 0 100 700 0 0 0 0 0 0").unwrap();
+    assert_eq!(times.refresh(path, at(0)), Some(first));
+    std::fs::write(path, "cpu  130 0 120 730 0 0 0 20 10 0").unwrap();
+    // the other usage sensors of the same tick get the same line
+    assert_eq!(times.refresh(path, at(1)), Some(first));
+    assert_eq!(times.refresh(path, at(500)), Some(second));
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(times.refresh(path, at(1000)), None);
+}
+
+#[tokio::test]
+async fn redetection_keeps_deltas() {
+    let detected = Detected::detect_in(&fixture("vm")).await;
+    let mut steal = CpuUsage::new(cpu::Kind::Steal);
+    assert!(steal.detect(&detected));
+    steal.usage(parse_times("cpu  100 0 100 700 0 0 0 0 0 0").unwrap());
+    // the same /proc/stat, the last counters stay
+    assert!(steal.detect(&detected));
+    let next = steal.usage(parse_times("cpu  130 0 120 730 0 0 0 20 10 0").unwrap());
+    assert_close(Reading::Value(next), &[20.0]);
+
+    let interfaces = scan(&fixture("vm"));
+    let settings = NetworkSettings::default();
+    let graphed = graphed(&interfaces, &settings);
+    let name = &graphed[0].name;
+    let dev = |bytes: u64| format!("{}: {} 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n", name, bytes);
+    let start = Instant::now();
+    let at = |secs: u64| start + Duration::from_secs(secs);
+    let mut rx = NetworkLoad::rx();
+    assert!(rx.detect(&detected));
+    rx.rates(&dev(1000), &graphed, at(0), &settings);
+    assert!(rx.detect(&detected));
+    let rates = rx.rates(&dev(2000), &graphed, at(1), &settings);
+    assert_close(Reading::Values(rates), &[1000.0]);
+}
+
+#[tokio::test]
+async fn cpu_freq_and_policy() {
+    let detected = Detected::detect_in(&fixture("intel-laptop")).await;
+    let mut freq = CpuFreq::new();
+    let mhz = [2100.0, 1800.0, 3400.0, 400.0, 2600.0, 1200.0, 400.0, 4200.0];
+    assert_close(sample(&mut freq, &detected), &mhz);
+    assert_eq!(freq.range(), (0.0, 4700.0));
+    let mut governor = CpuPolicy::new(Policy::Governor);
+    assert_close(sample(&mut governor, &detected), &[5.0]);
+    assert_eq!(governor.text().as_deref(), Some("powersave"));
+    let mut epp = CpuPolicy::new(Policy::Preference);
+    assert_close(sample(&mut epp, &detected), &[1.0]);
+    assert_eq!(epp.text().as_deref(), Some("balance_performance"));
+    let mut throttle = CpuThrottle::new();
+    let counts = [12.0, 0.0, 3.0, 0.0, 12.0, 0.0, 3.0, 0.0];
+    assert_close(sample(&mut throttle, &detected), &counts);
+    assert_eq!(throttle.range(), (0.0, 12.0));
+
+    // cpu10 is not cpu2, no EPP with acpi-cpufreq and no throttle counts on AMD
+    let detected = Detected::detect_in(&fixture("amd-desktop")).await;
+    let mut freq = CpuFreq::new();
+    let values = sample(&mut freq, &detected).values();
+    assert_eq!(values.len(), 12);
+    assert_eq!((values[2], values[10]), (3700.0, 4650.0));
+    let mut governor = CpuPolicy::new(Policy::Governor);
+    assert_close(sample(&mut governor, &detected), &[1.0]);
+    assert!(!CpuPolicy::new(Policy::Preference).detect(&detected));
+    assert!(!CpuThrottle::new().detect(&detected));
+
+    let detected = Detected::detect_in(&fixture("vm")).await;
+    assert!(!CpuFreq::new().detect(&detected));
+    assert!(!CpuPolicy::new(Policy::Governor).detect(&detected));
+}
+
+#[tokio::test]
+async fn memory_split() {
+    let detected = Detected::detect_in(&fixture("intel-laptop")).await;
+    // MemTotal - MemAvailable
+    let mut used = Memory::new(memory::Kind::Used);
+    assert_close(sample(&mut used, &detected), &[31.82]);
+    // apps, cache without Shmem, Shmem, free
+    let mut breakdown = Memory::new(memory::Kind::Breakdown);
+    let split = sample(&mut breakdown, &detected);
+    assert_close(split.clone(), &[28.35, 31.49, 3.89, 36.27]);
+    assert!((split.values().iter().sum::<f64>() - 100.0).abs() < 0.01);
+    // 512 MiB of 8 GiB
+    let mut swap = Memory::new(memory::Kind::Swap);
+    assert_close(sample(&mut swap, &detected), &[6.25]);
+    // 3 GiB in user-1000.slice
+    let mut session = Memory::new(memory::Kind::Session);
+    assert_close(sample(&mut session, &detected), &[19.65]);
+}
+
+#[tokio::test]
+async fn no_swap_and_no_session() {
+    let detected = Detected::detect_in(&fixture("amd-desktop")).await;
+    assert!(!Memory::new(memory::Kind::Swap).detect(&detected));
+    assert!(!Memory::new(memory::Kind::Session).detect(&detected));
+    assert!(Memory::new(memory::Kind::Used).detect(&detected));
+}
+
+#[tokio::test]
+async fn zram_compression() {
+    let root = fixture("intel-laptop");
+    let detected = Detected::detect_in(&root).await;
+    assert_eq!(found(&detected, &root, "zram.count").as_deref(), Some("1"));
+    let path = found(&detected, &root, "zram[0].path");
+    assert_eq!(path.as_deref(), Some("/sys/block/zram0"));
+    let devices = [root.join("sys/block/zram0"), root.join("sys/block/zram0")];
+    // two of the same add up
+    assert_eq!(
+        read_mm_stat(&devices),
+        Some((1073741824, 268435456, 285212672))
+    );
+    // 136 MiB of RAM for 512 MiB of swapped pages
+    let mut used = Zram::new(ZramKind::Used);
+    assert_close(sample(&mut used, &detected), &[0.87]);
+    let mut ratio = Zram::new(ZramKind::Ratio);
+    assert_close(sample(&mut ratio, &detected), &[4.0]);
+}
+
+#[tokio::test]
+async fn pressure_stall() {
+    let detected = Detected::detect_in(&fixture("intel-laptop")).await;
+    let mut io = Pressure::new(Resource::Io, Line::Some, Stat::Avg10);
+    assert_eq!(io.name(), "pressure.io.some");
+    assert_close(sample(&mut io, &detected), &[31.07]);
+    let mut memory = Pressure::new(Resource::Memory, Line::Full, Stat::Avg60);
+    assert_eq!(memory.name(), "pressure.memory.full.avg60");
+    assert_close(sample(&mut memory, &detected), &[2.4]);
+    let contents = "some avg10=0.12 avg60=0.05 avg300=0.01 total=1234567\n";
+    let stall = Stall {
+        avg10: 0.12,
+        avg60: 0.05,
+        avg300: 0.01,
+        total: 1234567,
+    };
+    assert_eq!(parse_stall(contents, Line::Some), Some(stall));
+    assert_eq!(parse_stall(contents, Line::Full), None);
+}
+
+#[tokio::test]
+async fn pressure_without_psi_or_cpu_full() {
+    // amd-desktop runs a kernel without PSI
+    let detected = Detected::detect_in(&fixture("amd-desktop")).await;
+    for resource in [Resource::Cpu, Resource::Memory, Resource::Io] {
+        assert!(!Pressure::new(resource, Line::Some, Stat::Avg10).detect(&detected));
+    }
+    // no `full` line for cpu before 5.13
+    let detected = Detected::detect_in(&fixture("vm")).await;
+    assert!(Pressure::new(Resource::Cpu, Line::Some, Stat::Rate).detect(&detected));
+    assert!(!Pressure::new(Resource::Cpu, Line::Full, Stat::Rate).detect(&detected));
+}
+
+#[test]
+fn pressure_rate() {
+    let mut rate = Pressure::new(Resource::Io, Line::Full, Stat::Rate);
+    let start = Instant::now();
+    assert_eq!(rate.rate(1_000_000, start), 0.0);
+    // 250 ms stalled in one second
+    let second = start + Duration::from_secs(1);
+    assert_close(Reading::Value(rate.rate(1_250_000, second)), &[25.0]);
+    // nothing new
+    let later = second + Duration::from_millis(500);
+    assert_eq!(rate.rate(1_250_000, later), 0.0);
+}
+
+#[test]
+fn process_stat() {
+    let stat = "2231 ((sd-pam)) S 1 2231 2231 0 -1 4194560 1000 0 10 0 7 5 0 0 20 0 1 0 2390 100";
+    let expected = processes::Stat {
+        comm: "(sd-pam)",
+        ticks: 12,
+        start: 2390,
+    };
+    assert_eq!(parse_stat(stat), Some(expected));
+    assert_eq!(parse_stat("2231 (sd-pam) S 1 2231"), None);
+}
+
+#[tokio::test]
+async fn top_processes() {
+    let detected = Detected::detect_in(&fixture("intel-laptop")).await;
+    let mut top = TopProcesses::new();
+    assert!(top.detect(&detected));
+    let start = Instant::now();
+    let first = top.walk(start, 3);
+    let pids = |list: &[processes::Process]| list.iter().map(|x| x.pid).collect::<Vec<_>>();
+    assert_eq!(pids(&first.by_memory), [1377, 2045, 1]);
+    let content = &first.by_memory[1];
+    assert_eq!(content.comm, "Web Content");
+    assert_eq!(
+        content.cmdline,
+        "/usr/lib/firefox/firefox -contentproc -childID 3 tab"
+    );
+    // statm counts pages
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    assert_eq!(content.rss, 90112 * page_size);
+    // nothing to compare to yet
+    assert_eq!(first.by_cpu.len(), 3);
+    assert!(first.by_cpu.iter().all(|x| x.cpu == 0.0));
+    // the same counters two seconds later, all idle; kernel threads have no cmdline
+    let second = top.walk(start + Duration::from_secs(2), 5);
+    assert!(second.by_cpu.iter().all(|x| x.cpu == 0.0));
+    let kthreadd = second.by_memory.iter().find(|x| x.pid == 2).unwrap();
+    assert_eq!((kthreadd.cmdline.as_str(), kthreadd.rss), ("", 0));
+}
+
+#[test]
+fn drm_fdinfo() {
+    let fixture = fixture("intel-laptop").join("proc/2045/fdinfo/31");
+    let i915 = parse_fdinfo(&std::fs::read_to_string(fixture).unwrap()).unwrap();
+    assert_eq!(i915.driver, "i915");
+    assert_eq!((i915.pdev.as_deref(), i915.id), (Some("0000:00:02.0"), 15));
+    let video = drm::Engine {
+        name: "video".to_string(),
+        busy: 8400000000,
+        total: None,
+        capacity: 2,
+    };
+    assert_eq!(i915.engines[2], video);
+    let names = i915
+        .engines
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["render", "copy", "video", "video-enhance"]);
+    let xe = "drm-driver:\txe\ndrm-client-id:\t9\ndrm-cycles-rcs:\t28257900\ndrm-total-cycles-rcs:\t7655183225\n";
+    let rcs = &parse_fdinfo(xe).unwrap().engines[0];
+    assert_eq!((rcs.busy, rcs.total), (28257900, Some(7655183225)));
+    // any other file
+    assert_eq!(
+        parse_fdinfo("pos:\t0\nflags:\t02000002\nmnt_id:\t15\n"),
+        None
+    );
+    assert!(Class::of("video-enhance") == Some(Class::Video));
+    assert!(Class::of("enc_1") == Some(Class::Video));
+    assert!(Class::of("gfx") == Some(Class::Render));
+    assert!(Class::of("ccs") == Some(Class::Compute));
+    assert!(Class::of("copy").is_none());
+}
+
+#[tokio::test]
+async fn drm_clients() {
+    let root = fixture("intel-laptop");
+    let detected = Detected::detect_in(&root).await;
+    let found = |key| found(&detected, &root, key);
+    assert_eq!(found("drm.count").as_deref(), Some("1"));
+    assert_eq!(found("drm[0].driver").as_deref(), Some("i915"));
+    assert_eq!(drm::devices(&detected), ["0000:00:02.0"]);
+    // fd 46 is a dup of 45, the same client
+    let clients = read_clients(&root.join("proc"));
+    let owners = clients
+        .iter()
+        .map(|x| (x.pid, x.comm.as_str(), x.client.id))
+        .collect::<Vec<_>>();
+    assert_eq!(owners, [(1377, "firefox", 12), (2045, "Web Content", 15)]);
+    let mut render = GpuEngines::new(Class::Render);
+    assert_close(sample(&mut render, &detected), &[0.0]);
+
+    let detected = Detected::detect_in(&fixture("amd-desktop")).await;
+    assert_eq!(drm::devices(&detected), ["0000:0c:00.0"]);
+    assert!(GpuEngines::new(Class::Compute).detect(&detected));
+    // no DRM card at all
+    let detected = Detected::detect_in(&fixture("vm")).await;
+    assert!(!GpuEngines::new(Class::Busy).detect(&detected));
+}
+
+#[test]
+fn drm_engine_usage() {
+    let mut clients = read_clients(&fixture("intel-laptop").join("proc"));
+    let devices = ["0000:00:02.0".to_string()];
+    let mut usage = DrmUsage::new();
+    let start = Instant::now();
+    usage.update(&clients, &devices, start);
+    assert_eq!(usage.gpus, [GpuLoad::default()]);
+    // a second later firefox rendered for 500 ms and the content process for
+    // 250 ms, next to 600 ms of video on two engines
+    clients[0].client.engines[0].busy += 500_000_000;
+    clients[1].client.engines[0].busy += 250_000_000;
+    clients[1].client.engines[2].busy += 600_000_000;
+    usage.update(&clients, &devices, start + Duration::from_secs(1));
+    let gpu = usage.gpus[0];
+    assert_close(
+        Reading::Values(vec![gpu.busy, gpu.render, gpu.video, gpu.compute]),
+        &[75.0, 75.0, 30.0, 0.0],
+    );
+    let processes = usage
+        .processes
+        .iter()
+        .map(|x| (x.pid, x.gpu, x.busy))
+        .collect::<Vec<_>>();
+    assert_eq!(processes, [(1377, 0, 50.0), (2045, 0, 30.0)]);
+
+    // xe counts cycles, msm has no PCI address
+    let xe = "drm-driver:\txe\ndrm-pdev:\t0000:00:02.0\ndrm-client-id:\t3\ndrm-cycles-ccs:\t1000\ndrm-total-cycles-ccs:\t10000\n";
+    let msm = "drm-driver:\tmsm\ndrm-client-id:\t3\ndrm-engine-gpu:\t0 ns\n";
+    let mut clients = [xe, msm].map(|contents| drm::ProcessClient {
+        pid: 1,
+        comm: "systemd".to_string(),
+        client: parse_fdinfo(contents).unwrap(),
+    });
+    let devices = ["0000:00:02.0".to_string(), "msm".to_string()];
+    let mut usage = DrmUsage::new();
+    usage.update(&clients, &devices, start);
+    let engine = &mut clients[0].client.engines[0];
+    engine.busy += 2000;
+    engine.total = Some(14000);
+    clients[1].client.engines[0].busy += 100_000_000;
+    usage.update(&clients, &devices, start + Duration::from_millis(500));
+    let compute = usage.gpus.iter().map(|x| x.compute).collect::<Vec<_>>();
+    let render = usage.gpus.iter().map(|x| x.render).collect::<Vec<_>>();
+    assert_eq!((compute, render), (vec![50.0, 0.0], vec![0.0, 20.0]));
+}
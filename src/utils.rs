@@ -14,20 +14,6 @@ macro_rules! global_init {
         }
     };
 }
-macro_rules! global_init_async {
-    ($name:ident, $type:ty, $initializer:expr) => {
-        pub async fn $name() -> &'static mut $type {
-            static mut VALUE: Option<$type> = None;
-            unsafe {
-                if VALUE.is_none() {
-                    let value = $initializer().await;
-                    VALUE.replace(value);
-                }
-                VALUE.as_mut().unwrap()
-            }
-        }
-    };
-}
 
 macro_rules! early_continue {
     ($condition:expr) => {
@@ -62,4 +48,3 @@ pub(crate) use early_return;
 pub(crate) use early_return_value;
 pub(crate) use global;
 pub(crate) use global_init;
-pub(crate) use global_init_async;
@@ -1,4 +1,4 @@
-use crate::config::Config;
+use crate::config::{config, Config};
 use colored::Colorize;
 use gtk4::{gdk::Monitor, prelude::*};
 use gtk4_layer_shell::LayerShell;
@@ -11,18 +11,16 @@ use std::collections::HashMap;
  ██║███╗██║██╔══██║██║     ██║     ██╔═══╝ ██╔══██║██╔═══╝ ██╔══╝  ██╔══██╗
  ╚███╔███╔╝██║  ██║███████╗███████╗██║     ██║  ██║██║     ███████╗██║  ██║
   ╚══╝╚══╝ ╚═╝  ╚═╝╚══════╝╚══════╝╚═╝     ╚═╝  ╚═╝╚═╝     ╚══════╝╚═╝  ╚═╝
+
+    One background layer window per monitor showing $config.wallpaper.path,
+    already resolved by the config, scaled to cover the monitor.
 */
 
 crate::utils::global!(wallpaper_windows, HashMap<gtk4::gdk::Monitor, gtk4::Window>, HashMap::new());
+crate::utils::global!(wallpaper_signals, bool, false);
 
 pub fn wallpaper_enabled(config: &Config) -> bool {
-    let wallpaper = config.config["wallpaper"]
-        .as_object()
-        .expect("$config.wallpaper is not an object")
-        .get("enabled")
-        .expect("$config.wallpaper.enabled is not set")
-        .as_bool()
-        .expect("$config.wallpaper.enabled is not a boolean");
+    let wallpaper = config.settings.wallpaper.enabled;
     println!(
         "[{}]: {}",
         "wallpaper".green(),
@@ -36,8 +34,16 @@ pub fn wallpaper_enabled(config: &Config) -> bool {
 
 pub fn init_wallpaper(config: &Config) {
     if !wallpaper_enabled(config) {
+        destroy_wallpaper_windows();
         return;
     }
+    if *wallpaper_signals() {
+        // a reload, windows that stay need the new settings too
+        diff_wallpaper_windows();
+        refresh_wallpaper_windows();
+        return;
+    }
+    *wallpaper_signals() = true;
     let display_manager = gtk4::gdk::DisplayManager::get();
     display_manager.connect_display_opened(|_, _| diff_wallpaper_windows());
     let screen = gtk4::gdk::Display::default().unwrap();
@@ -52,6 +58,19 @@ pub fn init_wallpaper(config: &Config) {
     diff_wallpaper_windows();
 }
 
+fn destroy_wallpaper_windows() {
+    let windows = wallpaper_windows();
+    for (_, w) in windows.drain() {
+        w.destroy();
+    }
+}
+
+fn refresh_wallpaper_windows() {
+    for window in wallpaper_windows().values() {
+        show_wallpaper(window);
+    }
+}
+
 fn diff_wallpaper_windows() {
     eprintln!(
         "[{}]: {}",
@@ -59,6 +78,11 @@ fn diff_wallpaper_windows() {
         "diff_wallpaper_windows".yellow()
     );
 
+    if !config().settings.wallpaper.enabled {
+        destroy_wallpaper_windows();
+        return;
+    }
+
     let screen = gtk4::gdk::Display::default().unwrap();
     let monitor = screen.monitors();
     let windows = wallpaper_windows();
@@ -134,8 +158,26 @@ fn create_wallpaper_for_monitor(monitor: &Monitor) {
     window.set_anchor(gtk4_layer_shell::Edge::Bottom, true);
 
     window.add_css_class("wallpaper");
+    show_wallpaper(&window);
 
     window.present();
 
     wallpaper_windows().insert(monitor.clone(), window);
 }
+
+/// Puts the configured image in `window`, a missing file leaves it empty.
+fn show_wallpaper(window: &gtk4::Window) {
+    let path = &config().settings.wallpaper.path;
+    if !std::path::Path::new(path).is_file() {
+        eprintln!(
+            "[{}]: {}({})",
+            "wallpaper".green(),
+            "missing".red(),
+            path.magenta()
+        );
+    }
+    let picture = gtk4::Picture::for_filename(path);
+    picture.set_content_fit(gtk4::ContentFit::Cover);
+    picture.set_can_shrink(true);
+    window.set_child(Some(&picture));
+}